@@ -0,0 +1,209 @@
+use clap::ValueEnum;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use brc::{
+    aggregate, aggregate_parallel, Compression, Error, OnError, Result, Stat, Stats, Summary,
+};
+
+use crate::cli::{BenchArgs, Strategy};
+use crate::{map_input, path_error};
+
+/// One line of the `--record` history file.
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: u64,
+    commit: Option<String>,
+    input: &'a Path,
+    strategy: &'a str,
+    stats: Vec<String>,
+    threads: usize,
+    cold: bool,
+    warmup: usize,
+    bytes: u64,
+    rows: u64,
+    times_ms: Vec<f64>,
+    min_ms: f64,
+    median_ms: f64,
+    p95_ms: f64,
+    rows_per_sec: f64,
+    gb_per_sec: f64,
+}
+
+pub fn bench(args: &BenchArgs) -> Result<()> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads.unwrap_or(0))
+        .build()
+        .map_err(|e| Error::Config(format!("could not start worker threads: {}", e)))?;
+    let strategy = args.strategy.to_possible_value().unwrap();
+    let bytes = std::fs::metadata(&args.input)
+        .map_err(path_error(&args.input))?
+        .len();
+
+    // Throughput is measured over the input as it's stored, which only
+    // means something when nothing has to be decompressed first.
+    if Compression::detect(&map_input(&args.input)?) != Compression::None {
+        return Err(Error::Argument(format!(
+            "{}: bench takes uncompressed input; decompress it first",
+            args.input.display()
+        )));
+    }
+
+    let mut rows = 0;
+    let mut times = Vec::with_capacity(args.iterations);
+
+    for i in 0..args.warmup + args.iterations {
+        if args.cold {
+            evict(&args.input)?;
+        }
+
+        let start = Instant::now();
+        let summary = pool.install(|| run(&args.input, args.strategy, &args.stats))?;
+        let elapsed = start.elapsed();
+
+        rows = summary.rows;
+
+        if i < args.warmup {
+            eprintln!("Warmup {}: {:?}", i + 1, elapsed);
+        } else {
+            eprintln!("Run {}: {:?}", i + 1 - args.warmup, elapsed);
+            times.push(elapsed);
+        }
+    }
+
+    let mut sorted = times.clone();
+    sorted.sort();
+
+    let min = sorted[0];
+    let median = median(&sorted);
+    let p95 = sorted[(sorted.len() * 95).div_ceil(100) - 1];
+    let rows_per_sec = rows as f64 / median.as_secs_f64();
+    let gb_per_sec = bytes as f64 / median.as_secs_f64() / 1e9;
+
+    let mut out = io::stdout().lock();
+
+    match args.strategy {
+        Strategy::Sequential => writeln!(out, "strategy    {}", strategy.get_name())?,
+        Strategy::Parallel => writeln!(
+            out,
+            "strategy    {} ({} threads)",
+            strategy.get_name(),
+            pool.current_num_threads()
+        )?,
+    }
+    writeln!(
+        out,
+        "runs        {} (+{} warmup, {})",
+        args.iterations,
+        args.warmup,
+        if args.cold { "cold" } else { "warm" }
+    )?;
+    writeln!(out, "min         {:?}", min)?;
+    writeln!(out, "median      {:?}", median)?;
+    writeln!(out, "p95         {:?}", p95)?;
+    writeln!(
+        out,
+        "throughput  {} rows/s, {:.2} GB/s",
+        (rows_per_sec as u64).to_formatted_string(&Locale::en),
+        gb_per_sec
+    )?;
+
+    if let Some(path) = &args.record {
+        let record = Record {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            commit: commit(),
+            input: &args.input,
+            strategy: strategy.get_name(),
+            stats: args.stats.iter().map(Stat::to_string).collect(),
+            threads: pool.current_num_threads(),
+            cold: args.cold,
+            warmup: args.warmup,
+            bytes,
+            rows,
+            times_ms: times.iter().map(ms).collect(),
+            min_ms: ms(&min),
+            median_ms: ms(&median),
+            p95_ms: ms(&p95),
+            rows_per_sec,
+            gb_per_sec,
+        };
+
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(path_error(path))?;
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        f.write_all(&line).map_err(path_error(path))?;
+    }
+
+    Ok(())
+}
+
+fn run(path: &Path, strategy: Strategy, stats: &[Stat]) -> Result<Summary<Stats>> {
+    let mmap = map_input(path)?;
+    let proto = Stats::new(stats);
+
+    let result = match strategy {
+        Strategy::Sequential => aggregate(&mmap, &proto, OnError::Skip),
+        Strategy::Parallel => aggregate_parallel(&mmap, &proto, OnError::Skip),
+    };
+
+    Ok(result.unwrap_or_else(|_| unreachable!("skip never fails")))
+}
+
+fn median(sorted: &[Duration]) -> Duration {
+    let mid = sorted.len() / 2;
+
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2
+    } else {
+        sorted[mid]
+    }
+}
+
+fn ms(d: &Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// The commit being benchmarked, if we're running inside a git checkout.
+fn commit() -> Option<String> {
+    let out = process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Drops the file's pages from the page cache so the next run reads from
+/// disk.
+#[cfg(target_os = "linux")]
+fn evict(path: &Path) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let f = std::fs::File::open(path).map_err(path_error(path))?;
+    f.sync_all().map_err(path_error(path))?;
+
+    match unsafe { libc::posix_fadvise(f.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) } {
+        0 => Ok(()),
+        e => Err(path_error(path)(io::Error::from_raw_os_error(e))),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn evict(_path: &Path) -> Result<()> {
+    Err(Error::Config(
+        "--cold is only supported on Linux".to_string(),
+    ))
+}
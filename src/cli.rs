@@ -0,0 +1,315 @@
+use clap::builder::RangedU64ValueParser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+use brc::{Compression, Format, OnError, Stat};
+
+/// The file name that stands for stdin or stdout.
+pub const STDIO: &str = "-";
+
+/// Naive Rust implementation of the 1 billion rows challenge.
+#[derive(Parser)]
+#[command(name = "brc", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate a measurements file of random readings.
+    #[command(alias = "create_measurements")]
+    Create(CreateArgs),
+
+    /// Aggregate min/mean/max per station.
+    Run(RunArgs),
+
+    /// Same as `run --parallel`.
+    #[command(name = "run_parallel", hide = true)]
+    RunParallel(RunArgs),
+
+    /// Check results against an expected results file.
+    Verify(VerifyArgs),
+
+    /// Time repeated runs of an aggregation strategy.
+    Bench(BenchArgs),
+
+    /// Combine partial aggregates written by `run --partial`.
+    Merge(MergeArgs),
+
+    /// Convert a measurements file to Parquet.
+    Convert(ConvertArgs),
+
+    /// Load a measurements file into an SQLite database.
+    ExportSqlite(ExportSqliteArgs),
+}
+
+#[derive(Args)]
+pub struct CreateArgs {
+    /// Number of rows to generate.
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    pub rows: u64,
+
+    /// Where to write the measurements, or `-` for stdout.
+    #[arg(short, long, default_value = "measurements.txt")]
+    pub output: PathBuf,
+
+    /// Write the measurements to stdout; same as `--output -`.
+    #[arg(long, conflicts_with = "output")]
+    pub stdout: bool,
+
+    /// Compress the measurements. Defaults to gzip for a `.gz` output and
+    /// zstd for a `.zst` one.
+    #[arg(long, value_enum)]
+    pub compress: Option<Compression>,
+
+    /// Seed for the random generator. The same seed and row count always
+    /// produce the same file; a random seed is picked and printed otherwise.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Also write the exact per-station results of the generated data to a
+    /// `.out` file next to the output, e.g. `measurements.out`.
+    #[arg(long)]
+    pub expected: bool,
+
+    /// Read stations from a `name;mean_temperature` file instead of using
+    /// the built-in list.
+    #[arg(long)]
+    pub stations: Option<PathBuf>,
+
+    /// Generate this many stations with random UTF-8 names instead of using
+    /// the built-in list.
+    #[arg(long, conflicts_with = "stations", value_parser = station_count)]
+    pub synthetic: Option<usize>,
+
+    /// Shortest synthetic station name, in bytes.
+    #[arg(long, default_value_t = 1, value_parser = name_bytes)]
+    pub min_name_bytes: usize,
+
+    /// Longest synthetic station name, in bytes.
+    #[arg(long, default_value_t = 100, value_parser = name_bytes)]
+    pub max_name_bytes: usize,
+}
+
+fn station_count(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(n) if (1..=10_000).contains(&n) => Ok(n),
+        _ => Err("must be a number of stations between 1 and 10000".to_string()),
+    }
+}
+
+fn name_bytes(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(n) if (1..=100).contains(&n) => Ok(n),
+        _ => Err("must be a number of bytes between 1 and 100".to_string()),
+    }
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Measurements file to read, or `-` for stdin. Gzip and zstd input is
+    /// decompressed on the fly.
+    #[arg(short, long, default_value = "measurements.txt")]
+    pub input: PathBuf,
+
+    /// Same as `--input`, e.g. `-` in `brc run -`.
+    #[arg(value_name = "INPUT", conflicts_with = "input")]
+    pub file: Option<PathBuf>,
+
+    /// Read the measurements from stdin; same as `--input -`.
+    #[arg(long, conflicts_with_all = ["input", "file"])]
+    pub stdin: bool,
+
+    /// Write results to this file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Aggregate newline-aligned chunks of the input on every core.
+    #[arg(short, long)]
+    pub parallel: bool,
+
+    /// Statistics to report per station, separated by commas.
+    #[arg(long, value_delimiter = ',', default_value = "min,mean,max")]
+    pub stats: Vec<Stat>,
+
+    /// Print one `name=count/...` line per station.
+    #[arg(long)]
+    pub counts: bool,
+
+    /// How to write the results.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Also save the full per-station aggregates to this file, to be
+    /// combined with others by `merge`.
+    #[arg(long)]
+    pub partial: Option<PathBuf>,
+
+    /// What to do with lines that can't be read.
+    #[arg(long, value_enum, default_value_t = OnError::Skip)]
+    pub on_error: OnError,
+
+    /// Write the `--on-error collect` report here instead of stderr.
+    #[arg(long)]
+    pub rejects: Option<PathBuf>,
+}
+
+impl RunArgs {
+    /// The input to read, with `-` standing for stdin.
+    pub fn input(&self) -> &Path {
+        if self.stdin {
+            Path::new(STDIO)
+        } else {
+            self.file.as_deref().unwrap_or(&self.input)
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Partial files written by `run --partial`.
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Write results to this file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Statistics to report per station, separated by commas.
+    #[arg(long, value_delimiter = ',', default_value = "min,mean,max")]
+    pub stats: Vec<Stat>,
+
+    /// Print one `name=count/...` line per station.
+    #[arg(long)]
+    pub counts: bool,
+
+    /// How to write the results.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Also save the merged aggregates as a new partial file.
+    #[arg(long)]
+    pub partial: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Measurements file to read.
+    #[arg(short, long, default_value = "measurements.txt")]
+    pub input: PathBuf,
+
+    /// Where to write the Parquet file.
+    #[arg(short, long, default_value = "measurements.parquet")]
+    pub output: PathBuf,
+
+    /// What to do with lines that can't be read.
+    #[arg(long, value_enum, default_value_t = OnError::Skip)]
+    pub on_error: OnError,
+
+    /// Write the `--on-error collect` report here instead of stderr.
+    #[arg(long)]
+    pub rejects: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ExportSqliteArgs {
+    /// Measurements file to read.
+    #[arg(short, long, default_value = "measurements.txt")]
+    pub input: PathBuf,
+
+    /// Database to create. Any existing file is replaced.
+    #[arg(short, long, default_value = "measurements.db")]
+    pub output: PathBuf,
+
+    /// Also write a `results` table with one row per station.
+    #[arg(long)]
+    pub results: bool,
+
+    /// Statistics to add as columns of the `results` table.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "min,mean,max",
+        requires = "results"
+    )]
+    pub stats: Vec<Stat>,
+
+    /// Add a `count` column to the `results` table.
+    #[arg(long, requires = "results")]
+    pub counts: bool,
+
+    /// What to do with lines that can't be read.
+    #[arg(long, value_enum, default_value_t = OnError::Skip)]
+    pub on_error: OnError,
+
+    /// Write the `--on-error collect` report here instead of stderr.
+    #[arg(long)]
+    pub rejects: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Expected results, as written by `create --expected`.
+    #[arg(short, long, default_value = "measurements.out")]
+    pub expected: PathBuf,
+
+    /// Measurements file to aggregate and check, or `-` for stdin.
+    #[arg(short, long, default_value = "measurements.txt")]
+    pub input: PathBuf,
+
+    /// Check this results file instead of aggregating the input.
+    #[arg(short, long, conflicts_with = "input")]
+    pub actual: Option<PathBuf>,
+
+    /// Aggregate newline-aligned chunks of the input on every core.
+    #[arg(short, long)]
+    pub parallel: bool,
+
+    /// Largest difference allowed between expected and actual values.
+    #[arg(long, default_value_t = 0.0)]
+    pub epsilon: f64,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Measurements file to read.
+    #[arg(short, long, default_value = "measurements.txt")]
+    pub input: PathBuf,
+
+    /// Aggregation strategy to time.
+    #[arg(short, long, value_enum, default_value_t = Strategy::Parallel)]
+    pub strategy: Strategy,
+
+    /// Number of timed runs.
+    #[arg(short = 'n', long, default_value_t = 5, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub iterations: usize,
+
+    /// Number of untimed runs before the timed ones.
+    #[arg(short, long, default_value_t = 0)]
+    pub warmup: usize,
+
+    /// Evict the input from the page cache before every run (Linux only).
+    #[arg(long)]
+    pub cold: bool,
+
+    /// Statistics to compute per station, separated by commas.
+    #[arg(long, value_delimiter = ',', default_value = "min,mean,max")]
+    pub stats: Vec<Stat>,
+
+    /// Worker threads for the parallel strategy; defaults to one per core.
+    #[arg(short, long)]
+    pub threads: Option<usize>,
+
+    /// Append the results as a JSON line to this history file.
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Strategy {
+    /// Aggregate the whole input on one thread.
+    Sequential,
+    /// Aggregate newline-aligned chunks on every core.
+    Parallel,
+}
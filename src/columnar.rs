@@ -0,0 +1,159 @@
+//! Arrow IPC and Parquet output, for results and for the raw measurements.
+
+use arrow_array::builder::{ArrayBuilder, Int16Builder, StringDictionaryBuilder};
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::results::Entry;
+use crate::stats::{Stat, Value};
+
+/// Rows per record batch, and so per Parquet row group, of measurements.
+const BATCH_ROWS: usize = 1 << 20;
+
+/// Writes results as an Arrow IPC file.
+pub fn write_ipc(out: &mut dyn Write, entries: &[Entry], stats: &[Stat]) -> Result<()> {
+    let batch = results_batch(entries, stats).map_err(arrow_error)?;
+
+    let mut writer = FileWriter::try_new(out, &batch.schema()).map_err(arrow_error)?;
+    writer.write(&batch).map_err(arrow_error)?;
+    writer.finish().map_err(arrow_error)
+}
+
+/// Writes results as a Parquet file.
+pub fn write_parquet(out: &mut dyn Write, entries: &[Entry], stats: &[Stat]) -> Result<()> {
+    let batch = results_batch(entries, stats).map_err(arrow_error)?;
+
+    // The writer wants a `Send` sink, which stdout isn't, and the results
+    // are small, so they're encoded in memory first.
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), Some(properties()))
+        .map_err(parquet_error)?;
+    writer.write(&batch).map_err(parquet_error)?;
+    writer.close().map_err(parquet_error)?;
+
+    Ok(out.write_all(&buf)?)
+}
+
+/// A `station` column followed by one column per statistic: counts as
+/// `uint64`, everything else as `float64`, null where undefined.
+fn results_batch(entries: &[Entry], stats: &[Stat]) -> Result<RecordBatch, ArrowError> {
+    let mut fields = vec![Field::new("station", DataType::Utf8, false)];
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(StringArray::from_iter_values(
+        entries.iter().map(|e| e.id),
+    ))];
+
+    for (i, stat) in stats.iter().enumerate() {
+        let values = entries.iter().map(|e| e.values[i]);
+
+        if *stat == Stat::Count {
+            fields.push(Field::new(stat.to_string(), DataType::UInt64, false));
+            columns.push(Arc::new(UInt64Array::from_iter_values(values.map(
+                |v| match v {
+                    Value::Count(n) => n,
+                    _ => unreachable!("count is always a Value::Count"),
+                },
+            ))));
+        } else {
+            fields.push(Field::new(stat.to_string(), DataType::Float64, true));
+            columns.push(Arc::new(Float64Array::from_iter(values.map(|v| match v {
+                Value::Count(n) => Some(n as f64),
+                Value::Tenths(t) => Some(t.as_f64()),
+                Value::Float(x) => x.is_finite().then_some(x),
+            }))));
+        }
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// Writes measurements to Parquet with a dictionary-encoded `station`
+/// column and an `int16` column of readings in tenths of a degree.
+pub struct MeasurementsWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    schema: Arc<Schema>,
+    stations: StringDictionaryBuilder<Int32Type>,
+    tenths: Int16Builder,
+}
+
+impl<W: Write + Send> MeasurementsWriter<W> {
+    pub fn new(out: W) -> Result<Self> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "station",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                false,
+            ),
+            Field::new("tenths", DataType::Int16, false),
+        ]));
+        let writer =
+            ArrowWriter::try_new(out, schema.clone(), Some(properties())).map_err(parquet_error)?;
+
+        Ok(MeasurementsWriter {
+            writer,
+            schema,
+            stations: StringDictionaryBuilder::new(),
+            tenths: Int16Builder::with_capacity(BATCH_ROWS),
+        })
+    }
+
+    pub fn push(&mut self, station: &str, tenths: i16) -> Result<()> {
+        self.stations.append_value(station);
+        self.tenths.append_value(tenths);
+
+        if self.tenths.len() == BATCH_ROWS {
+            self.write_rows()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the rows that are still buffered and the Parquet footer,
+    /// returning the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.tenths.is_empty() {
+            self.write_rows()?;
+        }
+
+        self.writer.into_inner().map_err(parquet_error)
+    }
+
+    /// Writes the rows built up so far as one batch, emptying the builders.
+    fn write_rows(&mut self) -> Result<()> {
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(self.stations.finish()),
+                Arc::new(self.tenths.finish()),
+            ],
+        )
+        .map_err(arrow_error)?;
+
+        self.writer.write(&batch).map_err(parquet_error)
+    }
+}
+
+fn properties() -> WriterProperties {
+    WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build()
+}
+
+fn arrow_error(e: ArrowError) -> Error {
+    match e {
+        ArrowError::IoError(_, e) => Error::from(e),
+        e => Error::from(io::Error::other(e)),
+    }
+}
+
+fn parquet_error(e: ParquetError) -> Error {
+    Error::from(io::Error::other(e))
+}
@@ -0,0 +1,261 @@
+//! Gzip and zstd compressed measurements.
+//!
+//! Compressed input is recognized by its magic bytes and decoded as a
+//! stream. A zstd file made of many frames, like the ones `create` writes,
+//! can also be decoded a batch of frames at a time on every thread.
+
+use clap::ValueEnum;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use rayon::prelude::*;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use zstd::zstd_safe;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How a file is compressed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// Plain text.
+    None,
+    /// Gzip, one member per block of rows.
+    Gzip,
+    /// Zstandard, one frame per block of rows.
+    Zstd,
+}
+
+impl Compression {
+    /// Recognizes compressed data by its first bytes.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if data.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Guesses from a `.gz` or `.zst` file extension.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Compresses `data` as a complete gzip member or zstd frame, so that
+    /// the results of separate calls can simply be concatenated.
+    pub fn compress(self, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(
+                    Vec::with_capacity(data.len() / 4),
+                    flate2::Compression::default(),
+                );
+                encoder.write_all(&data)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::stream::encode_all(&data[..], 0),
+        }
+    }
+}
+
+/// Decodes `data` according to its magic bytes, passing it through when
+/// it isn't compressed. With `parallel`, zstd data of more than one frame is
+/// decoded on every thread.
+pub fn decode(data: &[u8], parallel: bool) -> io::Result<Box<dyn Read + '_>> {
+    Ok(match Compression::detect(data) {
+        Compression::None => Box::new(data),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(data)),
+        Compression::Zstd if parallel && frame_size(data)? < data.len() => {
+            Box::new(ZstdFrames::new(data))
+        }
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(data)?),
+    })
+}
+
+/// Decodes a stream according to its magic bytes, passing it through when
+/// it isn't compressed.
+pub fn decode_reader<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match Compression::detect(reader.fill_buf()?) {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+    })
+}
+
+/// Reads the concatenated zstd frames of a buffer, decoding a batch of them
+/// at a time in parallel.
+pub struct ZstdFrames<'a> {
+    data: &'a [u8],
+    decoded: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a> ZstdFrames<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ZstdFrames {
+            data,
+            decoded: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Decodes the next batch of frames, enough to keep every thread busy.
+    fn decode_batch(&mut self) -> io::Result<()> {
+        let mut frames = Vec::new();
+
+        while frames.len() < rayon::current_num_threads() * 4 && !self.data.is_empty() {
+            let (frame, rest) = self.data.split_at(frame_size(self.data)?);
+            frames.push(frame);
+            self.data = rest;
+        }
+
+        let decoded = frames
+            .into_par_iter()
+            .map(zstd::stream::decode_all)
+            .collect::<io::Result<Vec<_>>>()?;
+
+        self.decoded = decoded.concat();
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl Read for ZstdFrames<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Skippable frames, like a seek table, decode to nothing.
+        while self.pos == self.decoded.len() && !self.data.is_empty() {
+            self.decode_batch()?;
+        }
+
+        let n = (&self.decoded[self.pos..]).read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// The compressed size of the zstd frame at the start of `data`.
+fn frame_size(data: &[u8]) -> io::Result<usize> {
+    zstd_safe::find_frame_compressed_size(data)
+        .map_err(|code| io::Error::new(io::ErrorKind::InvalidData, zstd_safe::get_error_name(code)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enough blocks for several batches of frames however many threads
+    /// there are.
+    fn blocks() -> Vec<Vec<u8>> {
+        (0..rayon::current_num_threads() * 12 + 5)
+            .map(|i| {
+                (0..50)
+                    .map(|j| format!("station{};{}.{}\n", (i * 50 + j) % 97, j % 40, i % 10))
+                    .collect::<String>()
+                    .into_bytes()
+            })
+            .collect()
+    }
+
+    fn compressed(compression: Compression, blocks: &[Vec<u8>]) -> Vec<u8> {
+        blocks
+            .iter()
+            .flat_map(|block| compression.compress(block.clone()).unwrap())
+            .collect()
+    }
+
+    fn read_all(mut reader: impl Read) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn zstd_frames_decode_in_parallel_like_in_sequence() {
+        let blocks = blocks();
+        let data = compressed(Compression::Zstd, &blocks);
+
+        assert!(Compression::detect(&data) == Compression::Zstd);
+        assert_eq!(
+            read_all(decode(&data, true).unwrap()).unwrap(),
+            blocks.concat()
+        );
+        assert_eq!(
+            read_all(decode(&data, false).unwrap()).unwrap(),
+            blocks.concat()
+        );
+        assert_eq!(
+            read_all(decode_reader(&data[..]).unwrap()).unwrap(),
+            blocks.concat()
+        );
+    }
+
+    #[test]
+    fn gzip_members_decode_one_after_another() {
+        let blocks = blocks();
+        let data = compressed(Compression::Gzip, &blocks);
+
+        assert!(Compression::detect(&data) == Compression::Gzip);
+        assert_eq!(
+            read_all(decode(&data, true).unwrap()).unwrap(),
+            blocks.concat()
+        );
+        assert_eq!(
+            read_all(decode_reader(&data[..]).unwrap()).unwrap(),
+            blocks.concat()
+        );
+    }
+
+    #[test]
+    fn plain_text_passes_through() {
+        let text = blocks().concat();
+
+        assert!(Compression::detect(&text) == Compression::None);
+        assert_eq!(read_all(decode(&text, true).unwrap()).unwrap(), text);
+        assert_eq!(read_all(decode_reader(&text[..]).unwrap()).unwrap(), text);
+    }
+
+    #[test]
+    fn skippable_frames_decode_to_nothing() {
+        let blocks = blocks();
+        let mut data = Compression::Zstd.compress(blocks[0].clone()).unwrap();
+
+        // A skippable frame: its magic, a little-endian size and a payload.
+        data.extend_from_slice(&0x184d_2a50u32.to_le_bytes());
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.extend_from_slice(b"skip!");
+        data.extend(compressed(Compression::Zstd, &blocks[1..]));
+
+        assert_eq!(
+            read_all(decode(&data, true).unwrap()).unwrap(),
+            blocks.concat()
+        );
+        assert_eq!(
+            read_all(decode(&data, false).unwrap()).unwrap(),
+            blocks.concat()
+        );
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let blocks = blocks();
+
+        for compression in [Compression::Zstd, Compression::Gzip] {
+            let data = compressed(compression, &blocks);
+            let data = &data[..data.len() - 7];
+
+            for parallel in [true, false] {
+                let decoded = decode(data, parallel).and_then(read_all);
+                assert!(decoded.is_err(), "parallel {}", parallel);
+            }
+
+            assert!(decode_reader(data).and_then(read_all).is_err());
+        }
+    }
+}
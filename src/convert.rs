@@ -0,0 +1,64 @@
+//! Converting a measurements file to Parquet.
+
+use num_format::{Locale, ToFormattedString};
+use std::io::Write;
+use std::time::Instant;
+
+use brc::columnar::MeasurementsWriter;
+use brc::reject::{parse_row, Reject, Rejects};
+use brc::{Error, Result};
+
+use crate::cli::ConvertArgs;
+use crate::{create_output, for_each_row, path_error, report_rejects};
+
+/// Converts a measurements file to Parquet with a dictionary-encoded
+/// `station` column and an `int16` column of readings in tenths of a degree.
+pub fn convert(args: &ConvertArgs) -> Result<()> {
+    let start = Instant::now();
+
+    let out = create_output(&args.output)?;
+    let mut rejects = Rejects::default();
+
+    // A file cut short by a bad line or a failed write is no use to anyone.
+    let rows = write_rows(args, out, &mut rejects).inspect_err(|_| {
+        let _ = std::fs::remove_file(&args.output);
+    })?;
+
+    report_rejects(&rejects, args.rejects.as_deref())?;
+
+    eprintln!(
+        "Converted {} rows in {:?}",
+        rows.to_formatted_string(&Locale::en),
+        start.elapsed()
+    );
+
+    Ok(())
+}
+
+/// Writes every row of the input that can be read, returning how many were.
+fn write_rows(args: &ConvertArgs, out: impl Write + Send, rejects: &mut Rejects) -> Result<u64> {
+    let output_error = |e: Error| e.at(&args.output);
+    let mut writer = MeasurementsWriter::new(out).map_err(output_error)?;
+    let mut rows: u64 = 0;
+
+    for_each_row(&args.input, |row, offset| {
+        match parse_row(row).and_then(|(id, reading)| Ok((id, reading.tenths()?))) {
+            Ok((id, tenths)) => {
+                writer.push(id, tenths).map_err(output_error)?;
+                rows += 1;
+            }
+            Err(reason) => rejects
+                .add(Reject::new(row, offset, reason), args.on_error)
+                .map_err(|r| Error::from(r).at(&args.input))?,
+        }
+
+        Ok(())
+    })?;
+
+    writer
+        .finish()
+        .map_err(output_error)?
+        .flush()
+        .map_err(path_error(&args.output))?;
+    Ok(rows)
+}
@@ -0,0 +1,129 @@
+//! The error type every command returns, and the exit code for each kind.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::reject::Reject;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing failed, on `path` or else on stdout or stderr.
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// Input couldn't be understood, in `path` when it came from a file.
+    Parse {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// The arguments can't be satisfied, e.g. a statistic that a partial
+    /// file didn't collect.
+    Argument(String),
+    /// This machine can't run the command as configured.
+    Config(String),
+    /// `verify` found differences.
+    Mismatch(String),
+}
+
+impl Error {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        }
+    }
+
+    pub fn parse(path: &Path, message: impl Into<String>) -> Self {
+        Error::Parse {
+            path: Some(path.to_path_buf()),
+            message: message.into(),
+        }
+    }
+
+    /// Attributes an error that didn't say which file it was about to `path`.
+    pub fn at(self, path: &Path) -> Self {
+        match self {
+            Error::Io { path: None, source } => Error::io(path, source),
+            Error::Parse {
+                path: None,
+                message,
+            } => Error::parse(path, message),
+            e => e,
+        }
+    }
+
+    /// The exit code for this error. Bad arguments share clap's 2, and the
+    /// rest follow `sysexits.h`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Mismatch(_) => 1,
+            Error::Argument(_) => 2,
+            Error::Parse { .. } => 65,
+            Error::Io { .. } => 74,
+            Error::Config(_) => 78,
+        }
+    }
+
+    /// Whether this is a write to a pipe whose reader has gone away, as with
+    /// `brc run | head`.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, Error::Io { source, .. } if source.kind() == io::ErrorKind::BrokenPipe)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::Io { path: None, source } => source.fmt(f),
+            Error::Parse {
+                path: Some(path),
+                message,
+            } => write!(f, "{}: {}", path.display(), message),
+            Error::Parse {
+                path: None,
+                message,
+            } => f.write_str(message),
+            Error::Argument(message) | Error::Config(message) | Error::Mismatch(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io { path: None, source }
+    }
+}
+
+impl From<Reject> for Error {
+    fn from(reject: Reject) -> Self {
+        Error::Parse {
+            path: None,
+            message: reject.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::from(io::Error::from(e))
+    }
+}
@@ -0,0 +1,876 @@
+//! Random measurements in the 1BRC format.
+//!
+//! Rows are produced in blocks of `BLOCK_ROWS`, each drawn from its own
+//! stream of a seeded ChaCha RNG. Blocks can therefore be rendered in any
+//! order or in parallel, and a seed always yields the same rows.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::reject::MAX_NAME_BYTES;
+use crate::stats::{Aggregator, Nums, Tenths};
+
+/// Rows per generator block. Each block draws from its own RNG stream, so
+/// the output for a seed doesn't depend on how many threads render it.
+pub const BLOCK_ROWS: u64 = 1 << 16;
+
+/// One generated row.
+#[derive(Clone, Copy)]
+pub struct Measurement<'a> {
+    pub station: &'a WeatherStation,
+    /// The reading in tenths of a degree.
+    pub tenths: i16,
+}
+
+/// The rows for a seed, in file order.
+pub struct Generator<'a> {
+    stations: &'a [WeatherStation],
+    seed: u64,
+    rows: u64,
+    next_block: u64,
+    block: Block<'a>,
+}
+
+/// The rows of a single block, as indices into the stations.
+struct Block<'a> {
+    stations: &'a [WeatherStation],
+    rng: ChaCha8Rng,
+    remaining: u64,
+}
+
+impl<'a> Generator<'a> {
+    /// Generates `rows` rows from `stations`, which must not be empty.
+    pub fn new(stations: &'a [WeatherStation], seed: u64, rows: u64) -> Self {
+        Generator {
+            stations,
+            seed,
+            rows,
+            next_block: 0,
+            block: Block::new(stations, seed, 0, 0),
+        }
+    }
+}
+
+impl<'a> Iterator for Generator<'a> {
+    type Item = Measurement<'a>;
+
+    fn next(&mut self) -> Option<Measurement<'a>> {
+        if self.block.remaining == 0 {
+            let start = self.next_block * BLOCK_ROWS;
+
+            if start >= self.rows {
+                return None;
+            }
+
+            let rows = BLOCK_ROWS.min(self.rows - start);
+            self.block = Block::new(self.stations, self.seed, self.next_block, rows);
+            self.next_block += 1;
+        }
+
+        self.block.next().map(|(i, tenths)| Measurement {
+            station: &self.stations[i],
+            tenths,
+        })
+    }
+}
+
+impl<'a> Block<'a> {
+    fn new(stations: &'a [WeatherStation], seed: u64, block: u64, rows: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(block);
+
+        Block {
+            stations,
+            rng,
+            remaining: rows,
+        }
+    }
+}
+
+impl Iterator for Block<'_> {
+    type Item = (usize, i16);
+
+    fn next(&mut self) -> Option<(usize, i16)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        // Same draw as `SliceRandom::choose`, but we need the index.
+        let i = self.rng.gen_range(0..self.stations.len() as u32) as usize;
+        let tenths = self.stations[i].measurement(&mut self.rng);
+
+        Some((i, tenths))
+    }
+}
+
+/// Formats the row as it appears in a measurements file, without the
+/// newline.
+impl fmt::Display for Measurement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{};{}", self.station.id(), Tenths(self.tenths as i64))
+    }
+}
+
+/// Renders block number `block`, of `rows` rows, as measurements file
+/// lines. When `track` is set, also returns the exact aggregate of what was
+/// written, indexed like `stations`.
+pub fn render_block(
+    stations: &[WeatherStation],
+    seed: u64,
+    block: u64,
+    rows: u64,
+    track: bool,
+) -> (Vec<u8>, Vec<Nums>) {
+    let mut buf = Vec::with_capacity(rows as usize * 16);
+    let mut nums = Vec::new();
+
+    if track {
+        nums.resize_with(stations.len(), Nums::new);
+    }
+
+    for (i, tenths) in Block::new(stations, seed, block, rows) {
+        writeln!(&mut buf, "{};{}", stations[i].id(), Tenths(tenths as i64)).unwrap();
+
+        if track {
+            nums[i].update(tenths);
+        }
+    }
+
+    (buf, nums)
+}
+
+/// Samples N(0, 1) with Marsaglia's polar method. This goes through `libm`
+/// rather than the platform math library so that a seeded RNG produces the
+/// same readings on every machine.
+fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    loop {
+        let u = rng.gen::<f64>() * 2.0 - 1.0;
+        let v = rng.gen::<f64>() * 2.0 - 1.0;
+        let s = u * u + v * v;
+
+        if s > 0.0 && s < 1.0 {
+            return u * libm::sqrt(-2.0 * libm::log(s) / s);
+        }
+    }
+}
+
+/// Builds a random name of exactly `len` bytes from a mix of ASCII, Latin,
+/// CJK and emoji characters, so names exercise every UTF-8 encoded width.
+fn synthetic_name<R: Rng + ?Sized>(rng: &mut R, len: usize) -> String {
+    let mut name = String::with_capacity(len);
+
+    while name.len() < len {
+        let c = match rng.gen_range(1..=(len - name.len()).min(4)) {
+            // Printable ASCII, minus the `;` delimiter.
+            1 => loop {
+                let c = rng.gen_range(b' '..=b'~') as char;
+
+                if c != ';' {
+                    break c;
+                }
+            },
+            2 => char::from_u32(rng.gen_range(0xC0..=0x24F)).unwrap(),
+            3 => char::from_u32(rng.gen_range(0x4E00..=0x9FFF)).unwrap(),
+            _ => char::from_u32(rng.gen_range(0x1F300..=0x1F5FF)).unwrap(),
+        };
+
+        name.push(c);
+    }
+
+    name
+}
+
+/// A station's name and the mean its readings are drawn around.
+#[derive(Debug)]
+pub struct WeatherStation(String, f64);
+
+impl WeatherStation {
+    pub fn new(id: impl Into<String>, mean: f64) -> Self {
+        WeatherStation(id.into(), mean)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.1
+    }
+
+    /// Samples a reading in tenths of a degree. Readings outside the 1BRC
+    /// range of -99.9..=99.9 are redrawn, and clamped if that keeps failing
+    /// (only possible for stations with an extreme mean).
+    pub fn measurement<R: Rng + ?Sized>(&self, rng: &mut R) -> i16 {
+        let mut tenths = 0.0;
+
+        for _ in 0..16 {
+            tenths = ((self.1 + 10.0 * standard_normal(rng)) * 10.0).round();
+
+            if (-999.0..=999.0).contains(&tenths) {
+                break;
+            }
+        }
+
+        tenths.clamp(-999.0, 999.0) as i16
+    }
+
+    /// Reads stations from a file in the upstream `weather_stations.csv`
+    /// format: one `name;mean_temperature` pair per line, with `#` comments.
+    pub fn load(path: &Path) -> Result<Vec<Self>> {
+        let text = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let mut stations = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |msg: &str| Error::parse(path, format!("line {}: {}", i + 1, msg));
+
+            let (name, mean) = line
+                .split_once(';')
+                .ok_or_else(|| invalid("expected `name;mean_temperature`"))?;
+            let mean = mean
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|mean| mean.is_finite())
+                .ok_or_else(|| invalid("invalid mean temperature"))?;
+
+            if name.is_empty() {
+                return Err(invalid("empty station name"));
+            }
+
+            // `run` would reject every row of such a station.
+            if name.len() > MAX_NAME_BYTES {
+                return Err(invalid("station name longer than 100 bytes"));
+            }
+
+            stations.push(WeatherStation(name.to_string(), mean));
+        }
+
+        if stations.is_empty() {
+            return Err(Error::parse(path, "no stations"));
+        }
+
+        Ok(stations)
+    }
+
+    /// Makes `n` stations with distinct random UTF-8 names whose lengths in
+    /// bytes fall within `name_bytes`, each with a mean between -15 and 35.
+    pub fn synthetic(n: usize, name_bytes: RangeInclusive<usize>, seed: u64) -> Result<Vec<Self>> {
+        // Block streams count up from zero, so this one never collides.
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(u64::MAX);
+
+        if name_bytes.is_empty() {
+            return Err(Error::Argument(
+                "minimum name length is longer than the maximum".to_string(),
+            ));
+        }
+
+        let mut names = HashSet::with_capacity(n);
+        let mut stations = Vec::with_capacity(n);
+        let mut attempts = 0;
+
+        while stations.len() < n {
+            attempts += 1;
+
+            if attempts > n * 100 {
+                return Err(Error::Argument(format!(
+                    "could not make {} distinct names of {}-{} bytes",
+                    n,
+                    name_bytes.start(),
+                    name_bytes.end()
+                )));
+            }
+
+            let len = rng.gen_range(name_bytes.clone());
+            let name = synthetic_name(&mut rng, len);
+
+            if names.insert(name.clone()) {
+                let mean = (rng.gen_range(-150..=350) as f64) / 10.0;
+                stations.push(WeatherStation(name, mean));
+            }
+        }
+
+        Ok(stations)
+    }
+
+    pub fn list() -> Vec<Self> {
+        vec![
+            WeatherStation("Abha".to_string(), 18.0),
+            WeatherStation("Abidjan".to_string(), 26.0),
+            WeatherStation("Abéché".to_string(), 29.4),
+            WeatherStation("Accra".to_string(), 26.4),
+            WeatherStation("Addis Ababa".to_string(), 16.0),
+            WeatherStation("Adelaide".to_string(), 17.3),
+            WeatherStation("Aden".to_string(), 29.1),
+            WeatherStation("Ahvaz".to_string(), 25.4),
+            WeatherStation("Albuquerque".to_string(), 14.0),
+            WeatherStation("Alexandra".to_string(), 11.0),
+            WeatherStation("Alexandria".to_string(), 20.0),
+            WeatherStation("Algiers".to_string(), 18.2),
+            WeatherStation("Alice Springs".to_string(), 21.0),
+            WeatherStation("Almaty".to_string(), 10.0),
+            WeatherStation("Amsterdam".to_string(), 10.2),
+            WeatherStation("Anadyr".to_string(), -6.9),
+            WeatherStation("Anchorage".to_string(), 2.8),
+            WeatherStation("Andorra la Vella".to_string(), 9.8),
+            WeatherStation("Ankara".to_string(), 12.0),
+            WeatherStation("Antananarivo".to_string(), 17.9),
+            WeatherStation("Antsiranana".to_string(), 25.2),
+            WeatherStation("Arkhangelsk".to_string(), 1.3),
+            WeatherStation("Ashgabat".to_string(), 17.1),
+            WeatherStation("Asmara".to_string(), 15.6),
+            WeatherStation("Assab".to_string(), 30.5),
+            WeatherStation("Astana".to_string(), 3.5),
+            WeatherStation("Athens".to_string(), 19.2),
+            WeatherStation("Atlanta".to_string(), 17.0),
+            WeatherStation("Auckland".to_string(), 15.2),
+            WeatherStation("Austin".to_string(), 20.7),
+            WeatherStation("Baghdad".to_string(), 22.77),
+            WeatherStation("Baguio".to_string(), 19.5),
+            WeatherStation("Baku".to_string(), 15.1),
+            WeatherStation("Baltimore".to_string(), 13.1),
+            WeatherStation("Bamako".to_string(), 27.8),
+            WeatherStation("Bangkok".to_string(), 28.6),
+            WeatherStation("Bangui".to_string(), 26.0),
+            WeatherStation("Banjul".to_string(), 26.0),
+            WeatherStation("Barcelona".to_string(), 18.2),
+            WeatherStation("Bata".to_string(), 25.1),
+            WeatherStation("Batumi".to_string(), 14.0),
+            WeatherStation("Beijing".to_string(), 12.9),
+            WeatherStation("Beirut".to_string(), 20.9),
+            WeatherStation("Belgrade".to_string(), 12.5),
+            WeatherStation("Belize City".to_string(), 26.7),
+            WeatherStation("Benghazi".to_string(), 19.9),
+            WeatherStation("Bergen".to_string(), 7.7),
+            WeatherStation("Berlin".to_string(), 10.3),
+            WeatherStation("Bilbao".to_string(), 14.7),
+            WeatherStation("Birao".to_string(), 26.5),
+            WeatherStation("Bishkek".to_string(), 11.3),
+            WeatherStation("Bissau".to_string(), 27.0),
+            WeatherStation("Blantyre".to_string(), 22.2),
+            WeatherStation("Bloemfontein".to_string(), 15.6),
+            WeatherStation("Boise".to_string(), 11.4),
+            WeatherStation("Bordeaux".to_string(), 14.2),
+            WeatherStation("Bosaso".to_string(), 30.0),
+            WeatherStation("Boston".to_string(), 10.9),
+            WeatherStation("Bouaké".to_string(), 26.0),
+            WeatherStation("Bratislava".to_string(), 10.5),
+            WeatherStation("Brazzaville".to_string(), 25.0),
+            WeatherStation("Bridgetown".to_string(), 27.0),
+            WeatherStation("Brisbane".to_string(), 21.4),
+            WeatherStation("Brussels".to_string(), 10.5),
+            WeatherStation("Bucharest".to_string(), 10.8),
+            WeatherStation("Budapest".to_string(), 11.3),
+            WeatherStation("Bujumbura".to_string(), 23.8),
+            WeatherStation("Bulawayo".to_string(), 18.9),
+            WeatherStation("Burnie".to_string(), 13.1),
+            WeatherStation("Busan".to_string(), 15.0),
+            WeatherStation("Cabo San Lucas".to_string(), 23.9),
+            WeatherStation("Cairns".to_string(), 25.0),
+            WeatherStation("Cairo".to_string(), 21.4),
+            WeatherStation("Calgary".to_string(), 4.4),
+            WeatherStation("Canberra".to_string(), 13.1),
+            WeatherStation("Cape Town".to_string(), 16.2),
+            WeatherStation("Changsha".to_string(), 17.4),
+            WeatherStation("Charlotte".to_string(), 16.1),
+            WeatherStation("Chiang Mai".to_string(), 25.8),
+            WeatherStation("Chicago".to_string(), 9.8),
+            WeatherStation("Chihuahua".to_string(), 18.6),
+            WeatherStation("Chișinău".to_string(), 10.2),
+            WeatherStation("Chittagong".to_string(), 25.9),
+            WeatherStation("Chongqing".to_string(), 18.6),
+            WeatherStation("Christchurch".to_string(), 12.2),
+            WeatherStation("City of San Marino".to_string(), 11.8),
+            WeatherStation("Colombo".to_string(), 27.4),
+            WeatherStation("Columbus".to_string(), 11.7),
+            WeatherStation("Conakry".to_string(), 26.4),
+            WeatherStation("Copenhagen".to_string(), 9.1),
+            WeatherStation("Cotonou".to_string(), 27.2),
+            WeatherStation("Cracow".to_string(), 9.3),
+            WeatherStation("Da Lat".to_string(), 17.9),
+            WeatherStation("Da Nang".to_string(), 25.8),
+            WeatherStation("Dakar".to_string(), 24.0),
+            WeatherStation("Dallas".to_string(), 19.0),
+            WeatherStation("Damascus".to_string(), 17.0),
+            WeatherStation("Dampier".to_string(), 26.4),
+            WeatherStation("Dar es Salaam".to_string(), 25.8),
+            WeatherStation("Darwin".to_string(), 27.6),
+            WeatherStation("Denpasar".to_string(), 23.7),
+            WeatherStation("Denver".to_string(), 10.4),
+            WeatherStation("Detroit".to_string(), 10.0),
+            WeatherStation("Dhaka".to_string(), 25.9),
+            WeatherStation("Dikson".to_string(), -11.1),
+            WeatherStation("Dili".to_string(), 26.6),
+            WeatherStation("Djibouti".to_string(), 29.9),
+            WeatherStation("Dodoma".to_string(), 22.7),
+            WeatherStation("Dolisie".to_string(), 24.0),
+            WeatherStation("Douala".to_string(), 26.7),
+            WeatherStation("Dubai".to_string(), 26.9),
+            WeatherStation("Dublin".to_string(), 9.8),
+            WeatherStation("Dunedin".to_string(), 11.1),
+            WeatherStation("Durban".to_string(), 20.6),
+            WeatherStation("Dushanbe".to_string(), 14.7),
+            WeatherStation("Edinburgh".to_string(), 9.3),
+            WeatherStation("Edmonton".to_string(), 4.2),
+            WeatherStation("El Paso".to_string(), 18.1),
+            WeatherStation("Entebbe".to_string(), 21.0),
+            WeatherStation("Erbil".to_string(), 19.5),
+            WeatherStation("Erzurum".to_string(), 5.1),
+            WeatherStation("Fairbanks".to_string(), -2.3),
+            WeatherStation("Fianarantsoa".to_string(), 17.9),
+            WeatherStation("Flores,  Petén".to_string(), 26.4),
+            WeatherStation("Frankfurt".to_string(), 10.6),
+            WeatherStation("Fresno".to_string(), 17.9),
+            WeatherStation("Fukuoka".to_string(), 17.0),
+            WeatherStation("Gabès".to_string(), 19.5),
+            WeatherStation("Gaborone".to_string(), 21.0),
+            WeatherStation("Gagnoa".to_string(), 26.0),
+            WeatherStation("Gangtok".to_string(), 15.2),
+            WeatherStation("Garissa".to_string(), 29.3),
+            WeatherStation("Garoua".to_string(), 28.3),
+            WeatherStation("George Town".to_string(), 27.9),
+            WeatherStation("Ghanzi".to_string(), 21.4),
+            WeatherStation("Gjoa Haven".to_string(), -14.4),
+            WeatherStation("Guadalajara".to_string(), 20.9),
+            WeatherStation("Guangzhou".to_string(), 22.4),
+            WeatherStation("Guatemala City".to_string(), 20.4),
+            WeatherStation("Halifax".to_string(), 7.5),
+            WeatherStation("Hamburg".to_string(), 9.7),
+            WeatherStation("Hamilton".to_string(), 13.8),
+            WeatherStation("Hanga Roa".to_string(), 20.5),
+            WeatherStation("Hanoi".to_string(), 23.6),
+            WeatherStation("Harare".to_string(), 18.4),
+            WeatherStation("Harbin".to_string(), 5.0),
+            WeatherStation("Hargeisa".to_string(), 21.7),
+            WeatherStation("Hat Yai".to_string(), 27.0),
+            WeatherStation("Havana".to_string(), 25.2),
+            WeatherStation("Helsinki".to_string(), 5.9),
+            WeatherStation("Heraklion".to_string(), 18.9),
+            WeatherStation("Hiroshima".to_string(), 16.3),
+            WeatherStation("Ho Chi Minh City".to_string(), 27.4),
+            WeatherStation("Hobart".to_string(), 12.7),
+            WeatherStation("Hong Kong".to_string(), 23.3),
+            WeatherStation("Honiara".to_string(), 26.5),
+            WeatherStation("Honolulu".to_string(), 25.4),
+            WeatherStation("Houston".to_string(), 20.8),
+            WeatherStation("Ifrane".to_string(), 11.4),
+            WeatherStation("Indianapolis".to_string(), 11.8),
+            WeatherStation("Iqaluit".to_string(), -9.3),
+            WeatherStation("Irkutsk".to_string(), 1.0),
+            WeatherStation("Istanbul".to_string(), 13.9),
+            WeatherStation("İzmir".to_string(), 17.9),
+            WeatherStation("Jacksonville".to_string(), 20.3),
+            WeatherStation("Jakarta".to_string(), 26.7),
+            WeatherStation("Jayapura".to_string(), 27.0),
+            WeatherStation("Jerusalem".to_string(), 18.3),
+            WeatherStation("Johannesburg".to_string(), 15.5),
+            WeatherStation("Jos".to_string(), 22.8),
+            WeatherStation("Juba".to_string(), 27.8),
+            WeatherStation("Kabul".to_string(), 12.1),
+            WeatherStation("Kampala".to_string(), 20.0),
+            WeatherStation("Kandi".to_string(), 27.7),
+            WeatherStation("Kankan".to_string(), 26.5),
+            WeatherStation("Kano".to_string(), 26.4),
+            WeatherStation("Kansas City".to_string(), 12.5),
+            WeatherStation("Karachi".to_string(), 26.0),
+            WeatherStation("Karonga".to_string(), 24.4),
+            WeatherStation("Kathmandu".to_string(), 18.3),
+            WeatherStation("Khartoum".to_string(), 29.9),
+            WeatherStation("Kingston".to_string(), 27.4),
+            WeatherStation("Kinshasa".to_string(), 25.3),
+            WeatherStation("Kolkata".to_string(), 26.7),
+            WeatherStation("Kuala Lumpur".to_string(), 27.3),
+            WeatherStation("Kumasi".to_string(), 26.0),
+            WeatherStation("Kunming".to_string(), 15.7),
+            WeatherStation("Kuopio".to_string(), 3.4),
+            WeatherStation("Kuwait City".to_string(), 25.7),
+            WeatherStation("Kyiv".to_string(), 8.4),
+            WeatherStation("Kyoto".to_string(), 15.8),
+            WeatherStation("La Ceiba".to_string(), 26.2),
+            WeatherStation("La Paz".to_string(), 23.7),
+            WeatherStation("Lagos".to_string(), 26.8),
+            WeatherStation("Lahore".to_string(), 24.3),
+            WeatherStation("Lake Havasu City".to_string(), 23.7),
+            WeatherStation("Lake Tekapo".to_string(), 8.7),
+            WeatherStation("Las Palmas de Gran Canaria".to_string(), 21.2),
+            WeatherStation("Las Vegas".to_string(), 20.3),
+            WeatherStation("Launceston".to_string(), 13.1),
+            WeatherStation("Lhasa".to_string(), 7.6),
+            WeatherStation("Libreville".to_string(), 25.9),
+            WeatherStation("Lisbon".to_string(), 17.5),
+            WeatherStation("Livingstone".to_string(), 21.8),
+            WeatherStation("Ljubljana".to_string(), 10.9),
+            WeatherStation("Lodwar".to_string(), 29.3),
+            WeatherStation("Lomé".to_string(), 26.9),
+            WeatherStation("London".to_string(), 11.3),
+            WeatherStation("Los Angeles".to_string(), 18.6),
+            WeatherStation("Louisville".to_string(), 13.9),
+            WeatherStation("Luanda".to_string(), 25.8),
+            WeatherStation("Lubumbashi".to_string(), 20.8),
+            WeatherStation("Lusaka".to_string(), 19.9),
+            WeatherStation("Luxembourg City".to_string(), 9.3),
+            WeatherStation("Lviv".to_string(), 7.8),
+            WeatherStation("Lyon".to_string(), 12.5),
+            WeatherStation("Madrid".to_string(), 15.0),
+            WeatherStation("Mahajanga".to_string(), 26.3),
+            WeatherStation("Makassar".to_string(), 26.7),
+            WeatherStation("Makurdi".to_string(), 26.0),
+            WeatherStation("Malabo".to_string(), 26.3),
+            WeatherStation("Malé".to_string(), 28.0),
+            WeatherStation("Managua".to_string(), 27.3),
+            WeatherStation("Manama".to_string(), 26.5),
+            WeatherStation("Mandalay".to_string(), 28.0),
+            WeatherStation("Mango".to_string(), 28.1),
+            WeatherStation("Manila".to_string(), 28.4),
+            WeatherStation("Maputo".to_string(), 22.8),
+            WeatherStation("Marrakesh".to_string(), 19.6),
+            WeatherStation("Marseille".to_string(), 15.8),
+            WeatherStation("Maun".to_string(), 22.4),
+            WeatherStation("Medan".to_string(), 26.5),
+            WeatherStation("Mek'ele".to_string(), 22.7),
+            WeatherStation("Melbourne".to_string(), 15.1),
+            WeatherStation("Memphis".to_string(), 17.2),
+            WeatherStation("Mexicali".to_string(), 23.1),
+            WeatherStation("Mexico City".to_string(), 17.5),
+            WeatherStation("Miami".to_string(), 24.9),
+            WeatherStation("Milan".to_string(), 13.0),
+            WeatherStation("Milwaukee".to_string(), 8.9),
+            WeatherStation("Minneapolis".to_string(), 7.8),
+            WeatherStation("Minsk".to_string(), 6.7),
+            WeatherStation("Mogadishu".to_string(), 27.1),
+            WeatherStation("Mombasa".to_string(), 26.3),
+            WeatherStation("Monaco".to_string(), 16.4),
+            WeatherStation("Moncton".to_string(), 6.1),
+            WeatherStation("Monterrey".to_string(), 22.3),
+            WeatherStation("Montreal".to_string(), 6.8),
+            WeatherStation("Moscow".to_string(), 5.8),
+            WeatherStation("Mumbai".to_string(), 27.1),
+            WeatherStation("Murmansk".to_string(), 0.6),
+            WeatherStation("Muscat".to_string(), 28.0),
+            WeatherStation("Mzuzu".to_string(), 17.7),
+            WeatherStation("N'Djamena".to_string(), 28.3),
+            WeatherStation("Naha".to_string(), 23.1),
+            WeatherStation("Nairobi".to_string(), 17.8),
+            WeatherStation("Nakhon Ratchasima".to_string(), 27.3),
+            WeatherStation("Napier".to_string(), 14.6),
+            WeatherStation("Napoli".to_string(), 15.9),
+            WeatherStation("Nashville".to_string(), 15.4),
+            WeatherStation("Nassau".to_string(), 24.6),
+            WeatherStation("Ndola".to_string(), 20.3),
+            WeatherStation("New Delhi".to_string(), 25.0),
+            WeatherStation("New Orleans".to_string(), 20.7),
+            WeatherStation("New York City".to_string(), 12.9),
+            WeatherStation("Ngaoundéré".to_string(), 22.0),
+            WeatherStation("Niamey".to_string(), 29.3),
+            WeatherStation("Nicosia".to_string(), 19.7),
+            WeatherStation("Niigata".to_string(), 13.9),
+            WeatherStation("Nouadhibou".to_string(), 21.3),
+            WeatherStation("Nouakchott".to_string(), 25.7),
+            WeatherStation("Novosibirsk".to_string(), 1.7),
+            WeatherStation("Nuuk".to_string(), -1.4),
+            WeatherStation("Odesa".to_string(), 10.7),
+            WeatherStation("Odienné".to_string(), 26.0),
+            WeatherStation("Oklahoma City".to_string(), 15.9),
+            WeatherStation("Omaha".to_string(), 10.6),
+            WeatherStation("Oranjestad".to_string(), 28.1),
+            WeatherStation("Oslo".to_string(), 5.7),
+            WeatherStation("Ottawa".to_string(), 6.6),
+            WeatherStation("Ouagadougou".to_string(), 28.3),
+            WeatherStation("Ouahigouya".to_string(), 28.6),
+            WeatherStation("Ouarzazate".to_string(), 18.9),
+            WeatherStation("Oulu".to_string(), 2.7),
+            WeatherStation("Palembang".to_string(), 27.3),
+            WeatherStation("Palermo".to_string(), 18.5),
+            WeatherStation("Palm Springs".to_string(), 24.5),
+            WeatherStation("Palmerston North".to_string(), 13.2),
+            WeatherStation("Panama City".to_string(), 28.0),
+            WeatherStation("Parakou".to_string(), 26.8),
+            WeatherStation("Paris".to_string(), 12.3),
+            WeatherStation("Perth".to_string(), 18.7),
+            WeatherStation("Petropavlovsk-Kamchatsky".to_string(), 1.9),
+            WeatherStation("Philadelphia".to_string(), 13.2),
+            WeatherStation("Phnom Penh".to_string(), 28.3),
+            WeatherStation("Phoenix".to_string(), 23.9),
+            WeatherStation("Pittsburgh".to_string(), 10.8),
+            WeatherStation("Podgorica".to_string(), 15.3),
+            WeatherStation("Pointe-Noire".to_string(), 26.1),
+            WeatherStation("Pontianak".to_string(), 27.7),
+            WeatherStation("Port Moresby".to_string(), 26.9),
+            WeatherStation("Port Sudan".to_string(), 28.4),
+            WeatherStation("Port Vila".to_string(), 24.3),
+            WeatherStation("Port-Gentil".to_string(), 26.0),
+            WeatherStation("Portland (OR)".to_string(), 12.4),
+            WeatherStation("Porto".to_string(), 15.7),
+            WeatherStation("Prague".to_string(), 8.4),
+            WeatherStation("Praia".to_string(), 24.4),
+            WeatherStation("Pretoria".to_string(), 18.2),
+            WeatherStation("Pyongyang".to_string(), 10.8),
+            WeatherStation("Rabat".to_string(), 17.2),
+            WeatherStation("Rangpur".to_string(), 24.4),
+            WeatherStation("Reggane".to_string(), 28.3),
+            WeatherStation("Reykjavík".to_string(), 4.3),
+            WeatherStation("Riga".to_string(), 6.2),
+            WeatherStation("Riyadh".to_string(), 26.0),
+            WeatherStation("Rome".to_string(), 15.2),
+            WeatherStation("Roseau".to_string(), 26.2),
+            WeatherStation("Rostov-on-Don".to_string(), 9.9),
+            WeatherStation("Sacramento".to_string(), 16.3),
+            WeatherStation("Saint Petersburg".to_string(), 5.8),
+            WeatherStation("Saint-Pierre".to_string(), 5.7),
+            WeatherStation("Salt Lake City".to_string(), 11.6),
+            WeatherStation("San Antonio".to_string(), 20.8),
+            WeatherStation("San Diego".to_string(), 17.8),
+            WeatherStation("San Francisco".to_string(), 14.6),
+            WeatherStation("San Jose".to_string(), 16.4),
+            WeatherStation("San José".to_string(), 22.6),
+            WeatherStation("San Juan".to_string(), 27.2),
+            WeatherStation("San Salvador".to_string(), 23.1),
+            WeatherStation("Sana'a".to_string(), 20.0),
+            WeatherStation("Santo Domingo".to_string(), 25.9),
+            WeatherStation("Sapporo".to_string(), 8.9),
+            WeatherStation("Sarajevo".to_string(), 10.1),
+            WeatherStation("Saskatoon".to_string(), 3.3),
+            WeatherStation("Seattle".to_string(), 11.3),
+            WeatherStation("Ségou".to_string(), 28.0),
+            WeatherStation("Seoul".to_string(), 12.5),
+            WeatherStation("Seville".to_string(), 19.2),
+            WeatherStation("Shanghai".to_string(), 16.7),
+            WeatherStation("Singapore".to_string(), 27.0),
+            WeatherStation("Skopje".to_string(), 12.4),
+            WeatherStation("Sochi".to_string(), 14.2),
+            WeatherStation("Sofia".to_string(), 10.6),
+            WeatherStation("Sokoto".to_string(), 28.0),
+            WeatherStation("Split".to_string(), 16.1),
+            WeatherStation("St. John's".to_string(), 5.0),
+            WeatherStation("St. Louis".to_string(), 13.9),
+            WeatherStation("Stockholm".to_string(), 6.6),
+            WeatherStation("Surabaya".to_string(), 27.1),
+            WeatherStation("Suva".to_string(), 25.6),
+            WeatherStation("Suwałki".to_string(), 7.2),
+            WeatherStation("Sydney".to_string(), 17.7),
+            WeatherStation("Tabora".to_string(), 23.0),
+            WeatherStation("Tabriz".to_string(), 12.6),
+            WeatherStation("Taipei".to_string(), 23.0),
+            WeatherStation("Tallinn".to_string(), 6.4),
+            WeatherStation("Tamale".to_string(), 27.9),
+            WeatherStation("Tamanrasset".to_string(), 21.7),
+            WeatherStation("Tampa".to_string(), 22.9),
+            WeatherStation("Tashkent".to_string(), 14.8),
+            WeatherStation("Tauranga".to_string(), 14.8),
+            WeatherStation("Tbilisi".to_string(), 12.9),
+            WeatherStation("Tegucigalpa".to_string(), 21.7),
+            WeatherStation("Tehran".to_string(), 17.0),
+            WeatherStation("Tel Aviv".to_string(), 20.0),
+            WeatherStation("Thessaloniki".to_string(), 16.0),
+            WeatherStation("Thiès".to_string(), 24.0),
+            WeatherStation("Tijuana".to_string(), 17.8),
+            WeatherStation("Timbuktu".to_string(), 28.0),
+            WeatherStation("Tirana".to_string(), 15.2),
+            WeatherStation("Toamasina".to_string(), 23.4),
+            WeatherStation("Tokyo".to_string(), 15.4),
+            WeatherStation("Toliara".to_string(), 24.1),
+            WeatherStation("Toluca".to_string(), 12.4),
+            WeatherStation("Toronto".to_string(), 9.4),
+            WeatherStation("Tripoli".to_string(), 20.0),
+            WeatherStation("Tromsø".to_string(), 2.9),
+            WeatherStation("Tucson".to_string(), 20.9),
+            WeatherStation("Tunis".to_string(), 18.4),
+            WeatherStation("Ulaanbaatar".to_string(), -0.4),
+            WeatherStation("Upington".to_string(), 20.4),
+            WeatherStation("Ürümqi".to_string(), 7.4),
+            WeatherStation("Vaduz".to_string(), 10.1),
+            WeatherStation("Valencia".to_string(), 18.3),
+            WeatherStation("Valletta".to_string(), 18.8),
+            WeatherStation("Vancouver".to_string(), 10.4),
+            WeatherStation("Veracruz".to_string(), 25.4),
+            WeatherStation("Vienna".to_string(), 10.4),
+            WeatherStation("Vientiane".to_string(), 25.9),
+            WeatherStation("Villahermosa".to_string(), 27.1),
+            WeatherStation("Vilnius".to_string(), 6.0),
+            WeatherStation("Virginia Beach".to_string(), 15.8),
+            WeatherStation("Vladivostok".to_string(), 4.9),
+            WeatherStation("Warsaw".to_string(), 8.5),
+            WeatherStation("Washington, D.C.".to_string(), 14.6),
+            WeatherStation("Wau".to_string(), 27.8),
+            WeatherStation("Wellington".to_string(), 12.9),
+            WeatherStation("Whitehorse".to_string(), -0.1),
+            WeatherStation("Wichita".to_string(), 13.9),
+            WeatherStation("Willemstad".to_string(), 28.0),
+            WeatherStation("Winnipeg".to_string(), 3.0),
+            WeatherStation("Wrocław".to_string(), 9.6),
+            WeatherStation("Xi'an".to_string(), 14.1),
+            WeatherStation("Yakutsk".to_string(), -8.8),
+            WeatherStation("Yangon".to_string(), 27.5),
+            WeatherStation("Yaoundé".to_string(), 23.8),
+            WeatherStation("Yellowknife".to_string(), -4.3),
+            WeatherStation("Yerevan".to_string(), 12.4),
+            WeatherStation("Yinchuan".to_string(), 9.0),
+            WeatherStation("Zagreb".to_string(), 10.7),
+            WeatherStation("Zanzibar City".to_string(), 26.0),
+            WeatherStation("Zürich".to_string(), 9.3),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_text(text: &str) -> Result<Vec<WeatherStation>> {
+        let path = std::env::temp_dir().join(format!("brc-stations-{}.csv", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let stations = WeatherStation::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        stations
+    }
+
+    #[test]
+    fn load_rejects_means_that_are_not_finite() {
+        for mean in ["nan", "NaN", "inf", "-inf", "1e400", ""] {
+            let error = load_text(&format!("A;10.0\nB;{}\n", mean)).err().unwrap();
+            assert!(
+                error
+                    .to_string()
+                    .ends_with("line 2: invalid mean temperature"),
+                "{}: {}",
+                mean,
+                error
+            );
+        }
+
+        let stations = load_text("# comment\nA;10.0\nB;-12.5\n").unwrap();
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[1].1, -12.5);
+    }
+
+    fn generated(stations: &[WeatherStation], seed: u64, rows: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        for m in Generator::new(stations, seed, rows) {
+            writeln!(out, "{}", m).unwrap();
+        }
+
+        out
+    }
+
+    /// Renders every block on a pool of `threads` threads, like `create`.
+    fn rendered(stations: &[WeatherStation], seed: u64, rows: u64, threads: usize) -> Vec<u8> {
+        use rayon::prelude::*;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+
+        pool.install(|| {
+            (0..rows.div_ceil(BLOCK_ROWS))
+                .into_par_iter()
+                .map(|block| {
+                    let n = BLOCK_ROWS.min(rows - block * BLOCK_ROWS);
+                    render_block(stations, seed, block, n, false).0
+                })
+                .collect::<Vec<_>>()
+                .concat()
+        })
+    }
+
+    #[test]
+    fn blocks_render_the_generator_rows() {
+        let stations = WeatherStation::list();
+        let rows = 2 * BLOCK_ROWS + 123;
+        let expected = generated(&stations, 7, rows);
+
+        assert_eq!(
+            expected.iter().filter(|&&b| b == b'\n').count() as u64,
+            rows
+        );
+        assert!(rendered(&stations, 7, rows, 1) == expected);
+        assert!(generated(&stations, 8, rows) != expected);
+    }
+
+    #[test]
+    fn output_does_not_depend_on_threads() {
+        let stations = WeatherStation::synthetic(500, 1..=100, 3).unwrap();
+        let rows = 5 * BLOCK_ROWS + 1;
+        let one = rendered(&stations, 11, rows, 1);
+
+        for threads in [2, 3, 8] {
+            assert!(
+                rendered(&stations, 11, rows, threads) == one,
+                "{} threads",
+                threads
+            );
+        }
+    }
+
+    #[test]
+    fn tracked_blocks_add_up_to_the_rows() {
+        let stations = WeatherStation::list();
+        let (text, nums) = render_block(&stations, 5, 2, 1000, true);
+
+        let mut expected = vec![Nums::new(); stations.len()];
+
+        for line in text.split(|&b| b == b'\n').filter(|l| !l.is_empty()) {
+            let line = std::str::from_utf8(line).unwrap();
+            let (name, reading) = line.rsplit_once(';').unwrap();
+            let i = stations.iter().position(|s| s.id() == name).unwrap();
+            expected[i].update(Tenths::round(reading.parse().unwrap()).0 as i16);
+        }
+
+        for (a, b) in nums.iter().zip(&expected) {
+            assert_eq!(
+                (a.count, a.sum, a.min, a.max),
+                (b.count, b.sum, b.min, b.max)
+            );
+        }
+    }
+
+    #[test]
+    fn synthetic_names_are_distinct_and_within_bounds() {
+        for (n, bytes) in [(3000, 1..=100), (90, 1..=1), (500, 4..=4), (200, 97..=100)] {
+            let stations = WeatherStation::synthetic(n, bytes.clone(), 9).unwrap();
+            let names: HashSet<&str> = stations.iter().map(|s| s.id()).collect();
+
+            assert_eq!(names.len(), n);
+
+            for name in names {
+                assert!(bytes.contains(&name.len()), "{:?}", name);
+                assert!(!name.contains([';', '\n']), "{:?}", name);
+            }
+
+            let again = WeatherStation::synthetic(n, bytes, 9).unwrap();
+            assert!(stations
+                .iter()
+                .map(|s| s.id())
+                .eq(again.iter().map(|s| s.id())));
+        }
+    }
+
+    #[test]
+    fn measurements_stay_in_range() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+
+        for mean in [0.0, 99.9, -99.9, 95.0, -150.0, 1e6, -1e300] {
+            let station = WeatherStation::new("x", mean);
+
+            for _ in 0..5000 {
+                let tenths = station.measurement(&mut rng);
+                assert!((-999..=999).contains(&tenths), "mean {}: {}", mean, tenths);
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! Aggregating 1BRC measurement files: `name;reading` lines, one per
+//! measurement.
+//!
+//! ```no_run
+//! use brc::{aggregate_reader, Format, OnError, Results, Stat, Stats};
+//!
+//! let stats = [Stat::Min, Stat::Mean, Stat::Max];
+//! let input = std::fs::File::open("measurements.txt")?;
+//! let summary = aggregate_reader(input, &Stats::new(&stats), OnError::Skip, true)?;
+//!
+//! let results = Results::new(&summary.nums);
+//! results.write(&mut std::io::stdout(), &stats, false, Format::Text)?;
+//! # Ok::<(), brc::Error>(())
+//! ```
+//!
+//! The `brc` binary is a command line front end to this crate.
+
+pub mod columnar;
+pub mod compress;
+pub mod error;
+pub mod generate;
+pub mod partial;
+pub mod reject;
+pub mod results;
+pub mod scan;
+pub mod stats;
+pub mod summary;
+pub mod table;
+
+pub use compress::Compression;
+pub use error::{Error, Result};
+pub use generate::{Generator, Measurement, WeatherStation};
+pub use reject::{OnError, Reject, Rejects};
+pub use results::{Format, Results};
+pub use stats::{Aggregator, Stat, Stats};
+pub use summary::{aggregate, aggregate_parallel, aggregate_reader, Summary};
@@ -0,0 +1,161 @@
+//! Partial aggregates that can be saved and merged later.
+//!
+//! A partial file holds the complete per-station aggregator state rather
+//! than rounded results, so merging any number of them gives exactly what
+//! aggregating all of their inputs at once would have.
+
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::error::{Error, Result};
+use crate::stats::{Aggregator, Stats};
+use crate::summary::Summary;
+
+/// Bumped whenever the serialized aggregator state changes shape.
+const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct Partial {
+    version: u32,
+    rows: u64,
+    stations: Vec<PartialStation>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PartialStation {
+    name: String,
+    stats: Stats,
+}
+
+/// Writes the aggregator state of `summary` to `out` as JSON.
+pub fn write_partial(out: impl Write, summary: &Summary<Stats>) -> Result<()> {
+    let partial = Partial {
+        version: VERSION,
+        rows: summary.rows,
+        stations: summary
+            .nums
+            .iter()
+            .map(|(id, stats)| PartialStation {
+                name: String::from_utf8_lossy(id).into_owned(),
+                stats: stats.clone(),
+            })
+            .collect(),
+    };
+
+    Ok(serde_json::to_writer(out, &partial)?)
+}
+
+/// Reads a summary written by `write_partial`.
+pub fn read_partial(reader: impl Read) -> Result<Summary<Stats>> {
+    let partial: Partial = serde_json::from_reader(reader).map_err(|e| {
+        if e.is_io() {
+            Error::from(e)
+        } else {
+            Error::Parse {
+                path: None,
+                message: e.to_string(),
+            }
+        }
+    })?;
+
+    if partial.version != VERSION {
+        return Err(Error::Parse {
+            path: None,
+            message: format!(
+                "partial file version {} is not supported, expected {}",
+                partial.version, VERSION
+            ),
+        });
+    }
+
+    let mut summary = Summary::new();
+    summary.rows = partial.rows;
+
+    for station in partial.stations {
+        check(&station)?;
+
+        let mut stats = Some(station.stats);
+        let entry = summary
+            .nums
+            .get_or_insert_with(station.name.as_bytes(), || stats.take().unwrap());
+
+        if let Some(stats) = stats {
+            entry.merge(&stats);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Rejects station state that aggregation could never have produced, and
+/// that finishing would choke on.
+fn check(station: &PartialStation) -> Result<()> {
+    let nums = &station.stats.nums;
+
+    let problem = if nums.count() == 0 {
+        "has no readings"
+    } else if nums.count > 0 && nums.min > nums.max {
+        "has a minimum above its maximum"
+    } else {
+        return Ok(());
+    };
+
+    Err(Error::Parse {
+        path: None,
+        message: format!("station {:?} {}", station.name, problem),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reject::OnError;
+    use crate::results::{Format, Results};
+    use crate::stats::Stat;
+    use crate::summary::aggregate;
+
+    /// The exact statistics: moments are merged in floating point, so they
+    /// only agree to within rounding.
+    const STATS: &[Stat] = &[
+        Stat::Sum,
+        Stat::Min,
+        Stat::Mean,
+        Stat::Max,
+        Stat::Median,
+        Stat::Mode,
+        Stat::Percentile(90.0),
+    ];
+
+    fn summarize(data: &[u8]) -> Summary<Stats> {
+        aggregate(data, &Stats::new(STATS), OnError::Skip).unwrap()
+    }
+
+    fn round_trip(summary: &Summary<Stats>) -> Summary<Stats> {
+        let mut json = Vec::new();
+        write_partial(&mut json, summary).unwrap();
+        read_partial(&json[..]).unwrap()
+    }
+
+    fn finished(summary: &Summary<Stats>) -> String {
+        let mut out = Vec::new();
+        Results::new(&summary.nums)
+            .write(&mut out, STATS, true, Format::Csv)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn merged_partials_equal_one_run_over_all_inputs() {
+        let monday: &[u8] = b"Abha;12.3\nOslo;-4.0\nAbha;-0.7\nLima;19.25\n";
+        let tuesday: &[u8] = b"Oslo;-3.9\nAbha;30.0\nLima;18.1\nOslo;-4.0\nTromso;-20.5\n";
+
+        let mut merged = round_trip(&summarize(monday));
+        merged.merge(round_trip(&summarize(tuesday)));
+        let all = summarize(&[monday, tuesday].concat());
+
+        assert_eq!(merged.rows, all.rows);
+        assert_eq!(finished(&merged), finished(&all));
+        assert!(finished(&all).contains("\nLima,2,37.4,18.1,18.7,19.3,18.1,18.1,19.25\n"));
+        assert!(finished(&all).ends_with("\nTromso,1,-20.5,-20.5,-20.5,-20.5,-20.5,-20.5,-20.5\n"));
+    }
+}
@@ -0,0 +1,317 @@
+//! Lines that can't be aggregated, and what `--on-error` does about them.
+
+use clap::ValueEnum;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+
+use crate::error::Result;
+use crate::scan::{parse_tenths, Row};
+use crate::stats::{Aggregator, Tenths};
+
+/// What to do about lines that can't be aggregated.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OnError {
+    /// Stop at the first line that can't be read.
+    Fail,
+    /// Skip such lines, printing how many there were.
+    Skip,
+    /// Skip such lines and report each one as a JSON line.
+    Collect,
+}
+
+/// Why a line was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    MissingSeparator,
+    InvalidName,
+    EmptyName,
+    /// Longer than the `MAX_NAME_BYTES` that 1BRC allows.
+    LongName,
+    InvalidReading,
+    /// A reading `convert` can't store as `int16` tenths.
+    OutOfRange,
+}
+
+/// The longest station name 1BRC allows, in bytes.
+pub const MAX_NAME_BYTES: usize = 100;
+
+const REASONS: [Reason; 6] = [
+    Reason::MissingSeparator,
+    Reason::InvalidName,
+    Reason::EmptyName,
+    Reason::LongName,
+    Reason::InvalidReading,
+    Reason::OutOfRange,
+];
+
+/// A rejected line, with where it starts in the input.
+#[derive(Clone)]
+pub struct Reject {
+    /// Line number, counting from 1.
+    pub line: u64,
+    /// Byte offset of the start of the line.
+    pub offset: u64,
+    pub reason: Reason,
+    /// The line itself, without its newline.
+    pub raw: Vec<u8>,
+}
+
+/// The rejected lines of some span of the input: how many there were for
+/// each reason, and the lines themselves when they're being collected.
+#[derive(Default)]
+pub struct Rejects {
+    counts: [u64; REASONS.len()],
+    kept: Vec<Reject>,
+}
+
+/// One entry of a `collect` report.
+#[derive(Serialize)]
+struct Entry {
+    line: u64,
+    offset: u64,
+    reason: Reason,
+    raw: String,
+}
+
+/// A reading as tenths of a degree when it's written `-?\d{1,2}\.\d`, or as
+/// any other finite number otherwise.
+#[derive(Clone, Copy)]
+pub enum Reading {
+    Tenths(i16),
+    Float(f64),
+}
+
+impl Reading {
+    pub fn parse(num: &[u8]) -> Result<Reading, Reason> {
+        if let Some(tenths) = parse_tenths(num) {
+            return Ok(Reading::Tenths(tenths));
+        }
+
+        std::str::from_utf8(num)
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|x| x.is_finite())
+            .map(Reading::Float)
+            .ok_or(Reason::InvalidReading)
+    }
+
+    pub fn add_to<A: Aggregator>(self, aggregate: &mut A) {
+        match self {
+            Reading::Tenths(tenths) => aggregate.update(tenths),
+            Reading::Float(num) => aggregate.update_f64(num),
+        }
+    }
+
+    /// The reading in tenths, rounding ones that aren't written
+    /// `-?\d{1,2}\.\d`.
+    pub fn tenths(self) -> Result<i16, Reason> {
+        match self {
+            Reading::Tenths(tenths) => Ok(tenths),
+            Reading::Float(num) => {
+                i16::try_from(Tenths::round(num).0).map_err(|_| Reason::OutOfRange)
+            }
+        }
+    }
+}
+
+/// Checks that a station name is 1 to `MAX_NAME_BYTES` bytes of UTF-8.
+pub fn parse_name(id: &[u8]) -> Result<&str, Reason> {
+    match id.len() {
+        0 => Err(Reason::EmptyName),
+        n if n > MAX_NAME_BYTES => Err(Reason::LongName),
+        _ => std::str::from_utf8(id).map_err(|_| Reason::InvalidName),
+    }
+}
+
+/// Splits a row into a valid station name and its reading.
+pub fn parse_row<'a>(row: &Row<'a>) -> Result<(&'a str, Reading), Reason> {
+    let (id, num) = row.split().ok_or(Reason::MissingSeparator)?;
+    let reading = Reading::parse(num)?;
+    let id = parse_name(id)?;
+
+    Ok((id, reading))
+}
+
+impl Rejects {
+    /// Records a rejected line according to `policy`, or hands it back as an
+    /// error under `fail`.
+    pub fn add(&mut self, reject: Reject, policy: OnError) -> Result<(), Reject> {
+        let reason = reject.reason;
+
+        match policy {
+            OnError::Fail => return Err(reject),
+            OnError::Skip => {}
+            OnError::Collect => self.kept.push(reject),
+        }
+
+        self.counts[reason as usize] += 1;
+        Ok(())
+    }
+
+    /// Adds the rejects of the input that follows ours, which starts after
+    /// `lines` lines of it.
+    pub fn merge(&mut self, other: Rejects, lines: u64) {
+        for (a, b) in self.counts.iter_mut().zip(other.counts) {
+            *a += b;
+        }
+
+        self.kept.extend(other.kept.into_iter().map(|mut reject| {
+            reject.line += lines;
+            reject
+        }));
+    }
+
+    /// How many lines were rejected, whether or not they were collected.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The collected lines, in input order.
+    pub fn kept(&self) -> &[Reject] {
+        &self.kept
+    }
+
+    /// Writes a JSON line per collected line to `out`.
+    pub fn write_report(&self, out: &mut dyn Write) -> Result<()> {
+        for reject in &self.kept {
+            let entry = Entry {
+                line: reject.line,
+                offset: reject.offset,
+                reason: reject.reason,
+                raw: escape(&reject.raw),
+            };
+
+            serde_json::to_writer(&mut *out, &entry)?;
+            writeln!(out)?;
+        }
+
+        Ok(out.flush()?)
+    }
+
+    /// How many lines were rejected for each reason, if any were.
+    pub fn summary(&self) -> Option<String> {
+        let total = self.total();
+
+        if total == 0 {
+            return None;
+        }
+
+        let reasons: Vec<String> = REASONS
+            .iter()
+            .filter(|&&r| self.counts[r as usize] > 0)
+            .map(|&r| {
+                format!(
+                    "{} ({})",
+                    r,
+                    self.counts[r as usize].to_formatted_string(&Locale::en)
+                )
+            })
+            .collect();
+
+        Some(format!(
+            "Rejected {} lines: {}",
+            total.to_formatted_string(&Locale::en),
+            reasons.join(", ")
+        ))
+    }
+}
+
+impl Reject {
+    /// Rejects `row`, which starts `offset` bytes into the input.
+    pub fn new(row: &Row, offset: u64, reason: Reason) -> Self {
+        Reject {
+            line: row.number,
+            offset: offset + row.start as u64,
+            reason,
+            raw: row.line.to_vec(),
+        }
+    }
+}
+
+/// What `fail` stops with: where the line is, what's wrong with it, and the
+/// line itself.
+impl fmt::Display for Reject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {} (byte {}): {}: {:?}",
+            self.line,
+            self.offset,
+            self.reason,
+            String::from_utf8_lossy(&self.raw)
+        )
+    }
+}
+
+impl fmt::Debug for Reject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Reject {}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Reason::MissingSeparator => "missing `;` separator",
+            Reason::InvalidName => "station name is not valid UTF-8",
+            Reason::EmptyName => "station name is empty",
+            Reason::LongName => "station name is longer than 100 bytes",
+            Reason::InvalidReading => "reading is not a number",
+            Reason::OutOfRange => "reading doesn't fit in an int16 of tenths",
+        })
+    }
+}
+
+/// The line as text, with any bytes that aren't valid UTF-8 written `\xNN`.
+fn escape(raw: &[u8]) -> String {
+    let mut s = String::with_capacity(raw.len());
+
+    for chunk in raw.utf8_chunks() {
+        s.push_str(chunk.valid());
+
+        for b in chunk.invalid() {
+            s.push_str(&format!("\\x{:02x}", b));
+        }
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenths_round_readings() {
+        assert_eq!(Reading::parse(b"-12.3").unwrap().tenths(), Ok(-123));
+        assert_eq!(Reading::parse(b"0.25").unwrap().tenths(), Ok(3));
+        assert_eq!(Reading::parse(b"3276.7").unwrap().tenths(), Ok(i16::MAX));
+    }
+
+    #[test]
+    fn tenths_reject_readings_out_of_range() {
+        for num in [&b"5000.5"[..], b"3276.8", b"-3276.9", b"1e9"] {
+            assert_eq!(
+                Reading::parse(num).unwrap().tenths(),
+                Err(Reason::OutOfRange)
+            );
+        }
+    }
+
+    #[test]
+    fn names_are_1_to_100_bytes_of_utf8() {
+        assert_eq!(parse_name(b"Abha"), Ok("Abha"));
+        assert_eq!(parse_name(&[b'a'; MAX_NAME_BYTES]).map(str::len), Ok(100));
+        assert_eq!(parse_name(b""), Err(Reason::EmptyName));
+        assert_eq!(
+            parse_name(&[b'a'; MAX_NAME_BYTES + 1]),
+            Err(Reason::LongName)
+        );
+        assert_eq!(parse_name(b"\xff"), Err(Reason::InvalidName));
+    }
+}
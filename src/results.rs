@@ -0,0 +1,265 @@
+//! Finished per-station results, and writing them in the formats `--format`
+//! offers.
+//!
+//! Every format lists stations sorted by name, with one value per requested
+//! statistic (and the count first with `--counts`). Only `text` is ambiguous
+//! when names contain `=`, `/` or `, `; the others escape them or, for the
+//! binary Arrow and Parquet formats, don't need to.
+
+use clap::ValueEnum;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::io::{self, Write};
+
+use crate::columnar;
+use crate::error::{Error, Result};
+use crate::stats::{Aggregator, Stat, Stats, StatsResult, Value};
+use crate::table::StationTable;
+
+/// How results are written.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// `{name=min/mean/max, ...}`, or one line per station with `--counts`.
+    Text,
+    /// A JSON array with one object per station.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// Tab-separated values with a header row.
+    Tsv,
+    /// An Arrow IPC file with one column per statistic.
+    Arrow,
+    /// A Parquet file with one column per statistic.
+    Parquet,
+}
+
+/// Every station's finished statistics, sorted by name.
+pub struct Results {
+    stations: Vec<(String, StatsResult)>,
+}
+
+/// One station's finished values, in the order they are reported.
+pub struct Entry<'a> {
+    pub id: &'a str,
+    pub stats: &'a [Stat],
+    pub values: Vec<Value>,
+}
+
+impl Results {
+    pub fn new(nums: &StationTable<Stats>) -> Self {
+        // Stations without readings have nothing to report.
+        let mut stations: Vec<(String, StatsResult)> = nums
+            .iter()
+            .filter(|(_, n)| n.nums.count() > 0)
+            // Aggregation only keeps UTF-8 names, but a table filled some
+            // other way may hold any bytes.
+            .map(|(id, n)| (String::from_utf8_lossy(id).into_owned(), n.finish()))
+            .collect();
+        stations.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        Results { stations }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    /// Stations in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &StatsResult)> {
+        self.stations
+            .iter()
+            .map(|(id, result)| (id.as_str(), result))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&StatsResult> {
+        self.stations
+            .binary_search_by(|(name, _)| name.as_str().cmp(id))
+            .ok()
+            .map(|i| &self.stations[i].1)
+    }
+
+    /// The values of `stats` for every station, failing if one of them
+    /// wasn't collected.
+    pub fn entries<'a>(&'a self, stats: &'a [Stat]) -> Result<Vec<Entry<'a>>> {
+        self.iter()
+            .map(|(id, result)| {
+                let values = stats
+                    .iter()
+                    .map(|&stat| {
+                        result.get(stat).ok_or_else(|| {
+                            Error::Argument(format!("{} was not collected for these results", stat))
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+
+                Ok(Entry { id, stats, values })
+            })
+            .collect()
+    }
+
+    /// Writes every station in `format`, with the values of `stats`
+    /// (preceded by the count with `counts`).
+    pub fn write(
+        &self,
+        out: &mut dyn Write,
+        stats: &[Stat],
+        counts: bool,
+        format: Format,
+    ) -> Result<()> {
+        let stats: Vec<Stat> = counts
+            .then_some(Stat::Count)
+            .into_iter()
+            .chain(stats.iter().copied())
+            .collect();
+        let entries = self.entries(&stats)?;
+
+        match format {
+            Format::Text => Ok(write_text(out, &entries, counts)?),
+            Format::Json => {
+                serde_json::to_writer(&mut *out, &entries)?;
+                Ok(writeln!(out)?)
+            }
+            Format::Ndjson => {
+                for entry in &entries {
+                    serde_json::to_writer(&mut *out, entry)?;
+                    writeln!(out)?;
+                }
+
+                Ok(())
+            }
+            Format::Csv => Ok(write_delimited(out, &entries, &stats, ',', csv_field)?),
+            Format::Tsv => Ok(write_delimited(out, &entries, &stats, '\t', tsv_field)?),
+            Format::Arrow => columnar::write_ipc(out, &entries, &stats),
+            Format::Parquet => columnar::write_parquet(out, &entries, &stats),
+        }
+    }
+}
+
+/// The 1BRC `{name=min/mean/max, ...}` format, or one `name=...` line per
+/// station with `--counts`.
+fn write_text(out: &mut dyn Write, entries: &[Entry], counts: bool) -> io::Result<()> {
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let values: Vec<String> = entry.values.iter().map(Value::to_string).collect();
+            format!("{}={}", entry.id, values.join("/"))
+        })
+        .collect();
+
+    if counts {
+        for entry in entries {
+            writeln!(out, "{}", entry)?;
+        }
+
+        Ok(())
+    } else {
+        writeln!(out, "{{{}}}", entries.join(", "))
+    }
+}
+
+/// A header row followed by one row per station, with every field passed
+/// through `escape`.
+fn write_delimited(
+    out: &mut dyn Write,
+    entries: &[Entry],
+    stats: &[Stat],
+    sep: char,
+    escape: fn(&str) -> String,
+) -> io::Result<()> {
+    let sep = sep.to_string();
+
+    let header: Vec<String> = std::iter::once("station".to_string())
+        .chain(stats.iter().map(Stat::to_string))
+        .map(|s| escape(&s))
+        .collect();
+    writeln!(out, "{}", header.join(&sep))?;
+
+    for entry in entries {
+        let row: Vec<String> = std::iter::once(escape(entry.id))
+            .chain(entry.values.iter().map(plain))
+            .collect();
+        writeln!(out, "{}", row.join(&sep))?;
+    }
+
+    Ok(())
+}
+
+/// A value as a bare number, at full precision, and empty when undefined.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::Float(x) if !x.is_finite() => String::new(),
+        Value::Float(x) => x.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+/// Quotes a field as RFC 4180 requires, doubling any quotes inside it.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Escapes tabs, newlines and backslashes, which TSV can't otherwise hold.
+fn tsv_field(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// `{"station": name, "<stat>": value, ...}`. Readings are numbers with one
+/// decimal, counts integers, and undefined values `null`.
+impl Serialize for Entry<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut map = s.serialize_map(Some(self.values.len() + 1))?;
+        map.serialize_entry("station", self.id)?;
+
+        for (stat, value) in self.stats.iter().zip(&self.values) {
+            let key = stat.to_string();
+
+            match *value {
+                Value::Count(n) => map.serialize_entry(&key, &n)?,
+                Value::Tenths(t) => map.serialize_entry(&key, &t.as_f64())?,
+                Value::Float(x) => map.serialize_entry(&key, &x)?,
+            }
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_that_are_not_utf8_are_replaced() {
+        let proto = Stats::new(&[Stat::Min]);
+        let mut nums = StationTable::new();
+        nums.get_or_insert_with(b"ok", || proto.clone()).update(10);
+        nums.get_or_insert_with(b"bad\xff", || proto.clone())
+            .update(20);
+
+        let results = Results::new(&nums);
+        let names: Vec<&str> = results.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["bad\u{fffd}", "ok"]);
+    }
+}
@@ -0,0 +1,346 @@
+//! Byte-oriented row scanning over a borrowed buffer.
+//!
+//! Delimiters are located eight bytes at a time with SWAR ("SIMD within a
+//! register") tricks, and rows are handed out as slices into the buffer, so
+//! nothing is copied or UTF-8 validated here. Input that only comes as a
+//! stream is read into a buffer a window of whole lines at a time.
+
+use std::io::{self, Read};
+
+/// How much of a reader `for_each_window` holds at once.
+const READ_BYTES: usize = 16 << 20;
+
+const LO: u64 = 0x0101_0101_0101_0101;
+const HI: u64 = 0x8080_8080_8080_8080;
+
+/// A single line of input, without its trailing newline.
+pub struct Row<'a> {
+    pub line: &'a [u8],
+    /// Byte offset of the line within the buffer.
+    pub start: usize,
+    /// Line number within the buffer, counting from 1.
+    pub number: u64,
+    sep: Option<usize>,
+}
+
+impl<'a> Row<'a> {
+    /// Splits the row into station name and reading at the first `;`.
+    pub fn split(&self) -> Option<(&'a [u8], &'a [u8])> {
+        self.sep.map(|i| (&self.line[..i], &self.line[i + 1..]))
+    }
+}
+
+/// Iterator over the non-empty rows of a buffer.
+pub struct Rows<'a> {
+    data: &'a [u8],
+    pos: usize,
+    lines: u64,
+}
+
+impl<'a> Rows<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Rows {
+            data,
+            pos: 0,
+            lines: 0,
+        }
+    }
+
+    /// How many lines have been consumed so far, empty ones included.
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Row<'a>;
+
+    fn next(&mut self) -> Option<Row<'a>> {
+        loop {
+            let start = self.pos;
+
+            if start >= self.data.len() {
+                return None;
+            }
+
+            let (sep, end) = match find_either(self.data, start, b';', b'\n') {
+                Some(i) if self.data[i] == b';' => {
+                    let end = find(self.data, i + 1, b'\n').unwrap_or(self.data.len());
+                    (Some(i - start), end)
+                }
+                Some(i) => (None, i),
+                None => (None, self.data.len()),
+            };
+
+            self.pos = end + 1;
+            self.lines += 1;
+
+            if end > start {
+                return Some(Row {
+                    line: &self.data[start..end],
+                    start,
+                    number: self.lines,
+                    sep,
+                });
+            }
+        }
+    }
+}
+
+/// Reads everything `reader` produces a bounded window at a time, handing
+/// `f` each window of whole lines along with its offset into the input. Only
+/// the last window may end without a newline.
+pub fn for_each_window<R, E>(
+    mut reader: R,
+    mut f: impl FnMut(&[u8], u64) -> Result<(), E>,
+) -> Result<(), E>
+where
+    R: Read,
+    E: From<io::Error>,
+{
+    let mut buf = vec![0; READ_BYTES];
+    let mut offset = 0;
+    let mut filled = 0;
+
+    loop {
+        let read = fill(&mut reader, &mut buf[filled..])?;
+        filled += read;
+
+        let eof = filled < buf.len();
+
+        // Only whole lines are handed out; the rest waits for more input.
+        let end = match buf[..filled].iter().rposition(|&b| b == b'\n') {
+            _ if eof => filled,
+            Some(i) => i + 1,
+            None => {
+                // A line longer than the window.
+                buf.resize(buf.len() * 2, 0);
+                continue;
+            }
+        };
+
+        f(&buf[..end], offset)?;
+
+        if eof {
+            return Ok(());
+        }
+
+        buf.copy_within(end..filled, 0);
+        filled -= end;
+        offset += end as u64;
+    }
+}
+
+/// Reads until `buf` is full or the reader runs out, returning how much was
+/// read.
+fn fill(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Returns a word with the high bit set in every byte of `word` equal to
+/// `b`. Bits above the lowest match may be spurious, which is fine since
+/// callers only ever look at the lowest one.
+#[inline]
+fn matches(word: u64, b: u8) -> u64 {
+    let x = word ^ (LO * b as u64);
+    x.wrapping_sub(LO) & !x & HI
+}
+
+#[inline]
+fn load(data: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes(data[i..i + 8].try_into().unwrap())
+}
+
+/// Position of the first `needle` at or after `from`.
+pub fn find(data: &[u8], from: usize, needle: u8) -> Option<usize> {
+    let mut i = from;
+
+    while i + 8 <= data.len() {
+        let m = matches(load(data, i), needle);
+
+        if m != 0 {
+            return Some(i + (m.trailing_zeros() / 8) as usize);
+        }
+
+        i += 8;
+    }
+
+    data[i..].iter().position(|&b| b == needle).map(|p| i + p)
+}
+
+/// Position of the first `a` or `b` at or after `from`.
+pub fn find_either(data: &[u8], from: usize, a: u8, b: u8) -> Option<usize> {
+    let mut i = from;
+
+    while i + 8 <= data.len() {
+        let word = load(data, i);
+        let m = matches(word, a) | matches(word, b);
+
+        if m != 0 {
+            return Some(i + (m.trailing_zeros() / 8) as usize);
+        }
+
+        i += 8;
+    }
+
+    data[i..]
+        .iter()
+        .position(|&c| c == a || c == b)
+        .map(|p| i + p)
+}
+
+/// Parses a reading of the form `-?\d{1,2}\.\d` into tenths of a degree.
+pub fn parse_tenths(b: &[u8]) -> Option<i16> {
+    let (neg, b) = match b {
+        [b'-', rest @ ..] => (true, rest),
+        _ => (false, b),
+    };
+
+    let tenths = match *b {
+        [d, b'.', t] if d.is_ascii_digit() && t.is_ascii_digit() => {
+            (d - b'0') as i16 * 10 + (t - b'0') as i16
+        }
+        [d1, d2, b'.', t] if d1.is_ascii_digit() && d2.is_ascii_digit() && t.is_ascii_digit() => {
+            (d1 - b'0') as i16 * 100 + (d2 - b'0') as i16 * 10 + (t - b'0') as i16
+        }
+        _ => return None,
+    };
+
+    Some(if neg { -tenths } else { tenths })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes that are, or differ in a single bit from, the needles, so that
+    /// a borrow or a high bit leaking between lanes would show up.
+    const ALPHABET: &[u8] = &[
+        b'a', b';', b'\n', b':', b'\x0b', 0xbb, 0x8a, 0x00, 0xff, 0x80,
+    ];
+
+    fn position(data: &[u8], from: usize, needles: &[u8]) -> Option<usize> {
+        data[from..]
+            .iter()
+            .position(|b| needles.contains(b))
+            .map(|p| from + p)
+    }
+
+    /// Every buffer of up to 24 bytes with a single needle in it, searched
+    /// from every offset, so the needle falls at every lane of a word, in
+    /// the word after the start and in the tail.
+    #[test]
+    fn find_single_needle_at_every_offset() {
+        for len in 0..=24 {
+            for at in 0..len {
+                for &filler in ALPHABET.iter().filter(|&&b| b != b';' && b != b'\n') {
+                    let mut data = vec![filler; len];
+                    data[at] = b';';
+
+                    for from in 0..=len {
+                        assert_eq!(
+                            find(&data, from, b';'),
+                            position(&data, from, b";"),
+                            "len {} at {} from {} filler {:#x}",
+                            len,
+                            at,
+                            from,
+                            filler
+                        );
+                        assert_eq!(
+                            find_either(&data, from, b';', b'\n'),
+                            position(&data, from, b";\n"),
+                            "len {} at {} from {} filler {:#x}",
+                            len,
+                            at,
+                            from,
+                            filler
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_without_needle() {
+        for len in 0..=24 {
+            let data = vec![b'a'; len];
+
+            for from in 0..=len {
+                assert_eq!(find(&data, from, b';'), None);
+                assert_eq!(find_either(&data, from, b';', b'\n'), None);
+            }
+        }
+    }
+
+    /// Pseudo-random buffers with several needles, where the first match
+    /// must win over any later ones in the same word.
+    #[test]
+    fn find_agrees_with_position_on_mixed_bytes() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+
+        for len in 0..=64 {
+            for _ in 0..32 {
+                let data: Vec<u8> = (0..len)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        ALPHABET[(state % ALPHABET.len() as u64) as usize]
+                    })
+                    .collect();
+
+                for from in 0..=len {
+                    assert_eq!(find(&data, from, b';'), position(&data, from, b";"));
+                    assert_eq!(find(&data, from, b'\n'), position(&data, from, b"\n"));
+                    assert_eq!(
+                        find_either(&data, from, b';', b'\n'),
+                        position(&data, from, b";\n")
+                    );
+                }
+            }
+        }
+    }
+
+    /// Windows must cover the input exactly, at their offsets, and end on
+    /// a newline unless they're the last, even with a line longer than a
+    /// window.
+    #[test]
+    fn windows_cover_input_in_whole_lines() {
+        let mut data = Vec::new();
+
+        for i in 0..READ_BYTES / 8 {
+            data.extend_from_slice(format!("s{};{}.{}\n", i % 97, i % 50, i % 10).as_bytes());
+        }
+
+        data.extend(std::iter::repeat_n(b'x', READ_BYTES * 2));
+        data.extend_from_slice(b";1.0\nlast;2.0");
+
+        let mut seen = Vec::new();
+        let mut ends = Vec::new();
+
+        for_each_window(&data[..], |window, offset| -> io::Result<()> {
+            assert_eq!(offset, seen.len() as u64);
+            seen.extend_from_slice(window);
+            ends.push(window.last().copied());
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(ends.len() > 2);
+        assert!(ends[..ends.len() - 1].iter().all(|&b| b == Some(b'\n')));
+        assert!(seen == data);
+    }
+}
@@ -0,0 +1,179 @@
+//! Bulk-loading measurements into SQLite, in the schema `python/runsql.py`
+//! uses, optionally along with a table of results.
+
+use num_format::{Locale, ToFormattedString};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use brc::reject::{parse_row, Reading, Reject, Rejects};
+use brc::results::Entry;
+use brc::stats::Value;
+use brc::table::StationTable;
+use brc::{Error, Result, Results, Stat, Stats};
+
+use crate::cli::ExportSqliteArgs;
+use crate::{for_each_row, path_error, report_rejects};
+
+pub fn export_sqlite(args: &ExportSqliteArgs) -> Result<()> {
+    let path = &args.output;
+    let start = Instant::now();
+    let columns = result_columns(args)?;
+
+    // Start from an empty database, like every other output we write.
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(path_error(path)(e)),
+        _ => {}
+    }
+
+    let mut rejects = Rejects::default();
+
+    // Without a journal a failed load can't be rolled back, and a database
+    // missing rows is no use to anyone anyway.
+    let rows = load(args, &columns, &mut rejects).inspect_err(|_| {
+        let _ = std::fs::remove_file(path);
+    })?;
+
+    report_rejects(&rejects, args.rejects.as_deref())?;
+
+    eprintln!(
+        "Loaded {} rows in {:?}",
+        rows.to_formatted_string(&Locale::en),
+        start.elapsed()
+    );
+
+    Ok(())
+}
+
+/// The statistics that make up the columns of the `results` table, checked
+/// before anything is loaded since a table can't have the same column twice.
+fn result_columns(args: &ExportSqliteArgs) -> Result<Vec<Stat>> {
+    let stats: Vec<Stat> = args
+        .counts
+        .then_some(Stat::Count)
+        .into_iter()
+        .chain(args.stats.iter().copied())
+        .collect();
+
+    for (i, stat) in stats.iter().enumerate() {
+        if stats[..i].contains(stat) {
+            return Err(Error::Argument(format!(
+                "{} would be a column of the results table twice",
+                stat
+            )));
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Fills a new database at the output path, returning how many rows were
+/// loaded.
+fn load(args: &ExportSqliteArgs, columns: &[Stat], rejects: &mut Rejects) -> Result<u64> {
+    let path = &args.output;
+    let mut conn = Connection::open(path).map_err(db_error(path))?;
+
+    // Nothing is gained from a journal while filling a fresh file.
+    conn.execute_batch(
+        "pragma journal_mode = off;
+         pragma synchronous = off;
+         create table measurements (id text, num real);",
+    )
+    .map_err(db_error(path))?;
+
+    let tx = conn.transaction().map_err(db_error(path))?;
+    let proto = Stats::new(&args.stats);
+    let mut nums = StationTable::new();
+    let mut rows: u64 = 0;
+
+    {
+        let mut insert = tx
+            .prepare("insert into measurements (id, num) values (?1, ?2)")
+            .map_err(db_error(path))?;
+
+        for_each_row(&args.input, |row, offset| {
+            let (id, reading) = match parse_row(row) {
+                Ok(parsed) => parsed,
+                Err(reason) => {
+                    return rejects
+                        .add(Reject::new(row, offset, reason), args.on_error)
+                        .map_err(|r| Error::from(r).at(&args.input));
+                }
+            };
+
+            // The results come from the same pass, so they cover exactly
+            // the rows that were loaded.
+            if args.results {
+                reading.add_to(nums.get_or_insert_with(id.as_bytes(), || proto.clone()));
+            }
+
+            // Dividing gives the same f64 as parsing the text, like Python's
+            // `float()` in `runsql.py`.
+            let num = match reading {
+                Reading::Tenths(tenths) => tenths as f64 / 10.0,
+                Reading::Float(num) => num,
+            };
+
+            insert.execute(params![id, num]).map_err(db_error(path))?;
+            rows += 1;
+            Ok(())
+        })?;
+    }
+
+    // Building the index once is much faster than updating it per row.
+    tx.execute_batch("create index idx_id on measurements(id);")
+        .map_err(db_error(path))?;
+    tx.commit().map_err(db_error(path))?;
+
+    if args.results {
+        let results = Results::new(&nums);
+        let entries = results.entries(columns)?;
+
+        write_results(&conn, &entries, columns).map_err(db_error(path))?;
+    }
+
+    Ok(rows)
+}
+
+/// Writes a `results` table with an `id` column followed by one column per
+/// statistic, named like the statistic.
+fn write_results(conn: &Connection, entries: &[Entry], stats: &[Stat]) -> rusqlite::Result<()> {
+    let columns: Vec<String> = stats
+        .iter()
+        .map(|stat| match stat {
+            Stat::Count => format!("\"{}\" integer", stat),
+            _ => format!("\"{}\" real", stat),
+        })
+        .collect();
+    conn.execute(
+        &format!(
+            "create table results (id text primary key, {})",
+            columns.join(", ")
+        ),
+        (),
+    )?;
+
+    let placeholders = vec!["?"; stats.len() + 1].join(", ");
+    let mut insert = conn.prepare(&format!("insert into results values ({})", placeholders))?;
+
+    for entry in entries {
+        let values = entry.values.iter().map(|value| match *value {
+            Value::Count(n) => SqlValue::Integer(n as i64),
+            Value::Tenths(t) => SqlValue::Real(t.as_f64()),
+            Value::Float(x) if x.is_finite() => SqlValue::Real(x),
+            Value::Float(_) => SqlValue::Null,
+        });
+
+        insert.execute(params_from_iter(
+            std::iter::once(SqlValue::Text(entry.id.to_string())).chain(values),
+        ))?;
+    }
+
+    Ok(())
+}
+
+fn db_error(path: &Path) -> impl FnOnce(rusqlite::Error) -> Error + '_ {
+    move |e| Error::io(path, io::Error::other(e))
+}
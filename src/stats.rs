@@ -0,0 +1,1116 @@
+//! Per-station statistics.
+//!
+//! Every statistic is an `Aggregator`: it is updated one reading at a time,
+//! partial aggregates from different chunks of the input are merged, and the
+//! final values are produced by `finish`. `Stats` bundles the aggregators
+//! needed for a set of `Stat`s chosen at runtime.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A per-station statistic that can be computed in pieces and combined.
+pub trait Aggregator: Clone + Send + Sync {
+    type Output;
+
+    /// Adds a reading in tenths of a degree.
+    fn update(&mut self, tenths: i16);
+
+    /// Adds a reading that isn't in the one-decimal fixed-point format.
+    fn update_f64(&mut self, num: f64);
+
+    /// Folds in an aggregate of other readings of the same station.
+    fn merge(&mut self, other: &Self);
+
+    fn finish(&self) -> Self::Output;
+}
+
+/// Count, sum, min, mean and max. Readings are kept as integer tenths of a
+/// degree so sums and means are exact; anything that isn't written with
+/// exactly one decimal place falls back to `FloatNums`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Nums {
+    pub count: u64,
+    pub sum: i64,
+    pub min: i16,
+    pub max: i16,
+    pub other: Option<FloatNums>,
+}
+
+/// Final values of `Nums`, rounded half up to tenths like the reference
+/// implementation. Exact unless some readings took the f64 path.
+#[derive(Clone, Copy)]
+pub struct NumsResult {
+    pub count: u64,
+    pub sum: Tenths,
+    pub min: Tenths,
+    pub mean: Tenths,
+    pub max: Tenths,
+}
+
+impl Nums {
+    pub fn new() -> Self {
+        Nums {
+            count: 0,
+            sum: 0,
+            min: i16::MAX,
+            max: i16::MIN,
+            other: None,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count + self.other.as_ref().map_or(0, |o| o.count)
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum as f64 / 10.0 + self.other.as_ref().map_or(0.0, |o| o.mean * o.count as f64)
+    }
+
+    pub fn min(&self) -> f64 {
+        let min = self.min as f64 / 10.0;
+
+        match &self.other {
+            Some(o) if self.count == 0 || o.min < min => o.min,
+            _ => min,
+        }
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum() / self.count() as f64
+    }
+
+    pub fn max(&self) -> f64 {
+        let max = self.max as f64 / 10.0;
+
+        match &self.other {
+            Some(o) if self.count == 0 || o.max > max => o.max,
+            _ => max,
+        }
+    }
+}
+
+impl Default for Nums {
+    fn default() -> Self {
+        Nums::new()
+    }
+}
+
+impl Aggregator for Nums {
+    type Output = NumsResult;
+
+    fn update(&mut self, tenths: i16) {
+        self.count += 1;
+        self.sum += tenths as i64;
+
+        if tenths < self.min {
+            self.min = tenths;
+        }
+
+        if tenths > self.max {
+            self.max = tenths;
+        }
+    }
+
+    fn update_f64(&mut self, num: f64) {
+        match self.other.as_mut() {
+            Some(other) => other.update(num),
+            None => {
+                self.other = Some(FloatNums {
+                    count: 1,
+                    min: num,
+                    mean: num,
+                    max: num,
+                })
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Nums) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+
+        match (self.other.as_mut(), other.other.as_ref()) {
+            (Some(a), Some(b)) => a.merge(b),
+            (None, Some(b)) => self.other = Some(b.clone()),
+            _ => {}
+        }
+    }
+
+    fn finish(&self) -> NumsResult {
+        match &self.other {
+            // Nothing to average; report zeros rather than divide by zero.
+            None if self.count == 0 => NumsResult {
+                count: 0,
+                sum: Tenths(0),
+                min: Tenths(0),
+                mean: Tenths(0),
+                max: Tenths(0),
+            },
+            None => {
+                let count = self.count as i64;
+
+                NumsResult {
+                    count: self.count,
+                    sum: Tenths(self.sum),
+                    min: Tenths(self.min as i64),
+                    mean: Tenths((2 * self.sum + count).div_euclid(2 * count)),
+                    max: Tenths(self.max as i64),
+                }
+            }
+            Some(_) => NumsResult {
+                count: self.count(),
+                sum: Tenths::round(self.sum()),
+                min: Tenths::round(self.min()),
+                mean: Tenths::round(self.mean()),
+                max: Tenths::round(self.max()),
+            },
+        }
+    }
+}
+
+/// Running aggregate for readings that don't fit the fixed-point format.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FloatNums {
+    pub count: u64,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+impl FloatNums {
+    pub fn update(&mut self, num: f64) {
+        self.count += 1;
+        self.mean += (num - self.mean) / (self.count as f64);
+
+        if num < self.min {
+            self.min = num;
+        }
+
+        if num > self.max {
+            self.max = num;
+        }
+    }
+
+    pub fn merge(&mut self, other: &FloatNums) {
+        let count = self.count + other.count;
+        self.mean += (other.mean - self.mean) * (other.count as f64 / count as f64);
+        self.count = count;
+
+        if other.min < self.min {
+            self.min = other.min;
+        }
+
+        if other.max > self.max {
+            self.max = other.max;
+        }
+    }
+}
+
+/// Central moments up to the fourth, for variance, standard deviation,
+/// skewness and kurtosis. Updated with Welford's method and merged with the
+/// pairwise formulas of Chan et al. and Pébay, so per-thread partials combine
+/// without losing precision.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Moments {
+    pub count: u64,
+    pub mean: f64,
+    pub m2: f64,
+    pub m3: f64,
+    pub m4: f64,
+}
+
+/// Final values of `Moments`. Variance and standard deviation are the
+/// sample (n - 1) estimates; skewness is the population g1 and kurtosis is
+/// the population excess kurtosis, so a normal distribution gives ~0 for both.
+#[derive(Clone, Copy)]
+pub struct MomentsResult {
+    pub variance: f64,
+    pub stddev: f64,
+    pub skewness: f64,
+    pub kurtosis: f64,
+}
+
+impl Moments {
+    pub fn new() -> Self {
+        Moments {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            m3: 0.0,
+            m4: 0.0,
+        }
+    }
+}
+
+impl Default for Moments {
+    fn default() -> Self {
+        Moments::new()
+    }
+}
+
+impl Aggregator for Moments {
+    type Output = MomentsResult;
+
+    fn update(&mut self, tenths: i16) {
+        self.update_f64(tenths as f64 / 10.0);
+    }
+
+    fn update_f64(&mut self, num: f64) {
+        let n1 = self.count as f64;
+        self.count += 1;
+        let n = self.count as f64;
+
+        let delta = num - self.mean;
+        let delta_n = delta / n;
+        let delta_n2 = delta_n * delta_n;
+        let term = delta * delta_n * n1;
+
+        self.mean += delta_n;
+        self.m4 += term * delta_n2 * (n * n - 3.0 * n + 3.0) + 6.0 * delta_n2 * self.m2
+            - 4.0 * delta_n * self.m3;
+        self.m3 += term * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term;
+    }
+
+    fn merge(&mut self, other: &Moments) {
+        if other.count == 0 {
+            return;
+        }
+
+        if self.count == 0 {
+            *self = other.clone();
+            return;
+        }
+
+        let (na, nb) = (self.count as f64, other.count as f64);
+        let n = na + nb;
+        let delta = other.mean - self.mean;
+        let delta2 = delta * delta;
+
+        let m2 = self.m2 + other.m2 + delta2 * na * nb / n;
+        let m3 = self.m3
+            + other.m3
+            + delta * delta2 * na * nb * (na - nb) / (n * n)
+            + 3.0 * delta * (na * other.m2 - nb * self.m2) / n;
+        let m4 = self.m4
+            + other.m4
+            + delta2 * delta2 * na * nb * (na * na - na * nb + nb * nb) / (n * n * n)
+            + 6.0 * delta2 * (na * na * other.m2 + nb * nb * self.m2) / (n * n)
+            + 4.0 * delta * (na * other.m3 - nb * self.m3) / n;
+
+        self.count += other.count;
+        self.mean += delta * nb / n;
+        self.m2 = m2;
+        self.m3 = m3;
+        self.m4 = m4;
+    }
+
+    fn finish(&self) -> MomentsResult {
+        let n = self.count as f64;
+        let variance = self.m2 / (n - 1.0);
+
+        MomentsResult {
+            variance,
+            stddev: variance.sqrt(),
+            skewness: n.sqrt() * self.m3 / self.m2.powf(1.5),
+            kurtosis: n * self.m4 / (self.m2 * self.m2) - 3.0,
+        }
+    }
+}
+
+/// Readings in tenths range over -999..=999.
+const MIN_TENTHS: i16 = -999;
+const MAX_TENTHS: i16 = 999;
+
+/// Exact distribution of a station's readings: a counter per tenth of a
+/// degree, plus a counter per distinct reading that took the f64 path.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Histogram {
+    pub buckets: Buckets,
+    pub others: Others,
+}
+
+/// Counters for every tenth of a degree between the lowest and highest
+/// reading seen so far. Most stations only ever see a fraction of the
+/// possible range, and a station seen once in a chunk costs a single
+/// counter rather than one for every possible reading.
+#[derive(Clone, Default)]
+pub struct Buckets {
+    low: i16,
+    counts: Vec<u64>,
+}
+
+/// Counters for the distinct readings that aren't in tenths, in order of
+/// value. Files written with more decimals can have a great many readings,
+/// but only so many distinct ones.
+#[derive(Clone, Default)]
+pub struct Others {
+    counts: BTreeMap<i64, u64>,
+}
+
+/// Everything needed to answer quantile and mode queries after the fact.
+#[derive(Clone)]
+pub struct HistogramResult {
+    buckets: Buckets,
+    others: Others,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Histogram {
+            buckets: Buckets::default(),
+            others: Others::default(),
+        }
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram::new()
+    }
+}
+
+impl Aggregator for Histogram {
+    type Output = HistogramResult;
+
+    fn update(&mut self, tenths: i16) {
+        self.buckets.add(tenths, 1);
+    }
+
+    fn update_f64(&mut self, num: f64) {
+        self.others.add(num, 1);
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        self.buckets.merge(&other.buckets);
+        self.others.merge(&other.others);
+    }
+
+    fn finish(&self) -> HistogramResult {
+        HistogramResult {
+            count: self.buckets.counts.iter().sum::<u64>()
+                + self.others.counts.values().sum::<u64>(),
+            buckets: self.buckets.clone(),
+            others: self.others.clone(),
+        }
+    }
+}
+
+impl Buckets {
+    /// Counts `n` more readings of `tenths`.
+    pub fn add(&mut self, tenths: i16, n: u64) {
+        self.cover(tenths, tenths);
+        self.counts[offset(self.low, tenths)] += n;
+    }
+
+    pub fn merge(&mut self, other: &Buckets) {
+        let Some(high) = other.high() else {
+            return;
+        };
+
+        self.cover(other.low, high);
+        let start = offset(self.low, other.low);
+
+        for (a, b) in self.counts[start..].iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+    }
+
+    /// The readings with a non-zero count, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (i16, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &n)| n > 0)
+            .map(|(i, &n)| ((self.low as i32 + i as i32) as i16, n))
+    }
+
+    fn high(&self) -> Option<i16> {
+        (!self.counts.is_empty()).then(|| (self.low as i32 + self.counts.len() as i32 - 1) as i16)
+    }
+
+    /// Grows the counters to span `low..=high` as well.
+    fn cover(&mut self, low: i16, high: i16) {
+        let Some(own_high) = self.high() else {
+            self.low = low;
+            self.counts = vec![0; offset(low, high) + 1];
+            return;
+        };
+
+        if low < self.low {
+            let extra = offset(low, self.low);
+            self.counts.splice(0..0, std::iter::repeat_n(0, extra));
+            self.low = low;
+        }
+
+        if high > own_high {
+            self.counts.resize(offset(self.low, high) + 1, 0);
+        }
+    }
+}
+
+/// How far `tenths` is above `low`, which it must not be below.
+fn offset(low: i16, tenths: i16) -> usize {
+    (tenths as i32 - low as i32) as usize
+}
+
+/// Serialized as `[tenths, count]` pairs for the non-empty buckets only,
+/// which keeps partial files small.
+impl Serialize for Buckets {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for Buckets {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let mut buckets = Buckets::default();
+
+        for (tenths, n) in Vec::<(i64, u64)>::deserialize(d)? {
+            if !(MIN_TENTHS as i64..=MAX_TENTHS as i64).contains(&tenths) {
+                return Err(serde::de::Error::custom(format!(
+                    "histogram bucket {} is out of range",
+                    tenths
+                )));
+            }
+
+            buckets.add(tenths as i16, n);
+        }
+
+        Ok(buckets)
+    }
+}
+
+impl Others {
+    /// Counts `n` more readings of `num`.
+    pub fn add(&mut self, num: f64, n: u64) {
+        *self.counts.entry(key(num)).or_default() += n;
+    }
+
+    pub fn merge(&mut self, other: &Others) {
+        for (&k, &n) in &other.counts {
+            *self.counts.entry(k).or_default() += n;
+        }
+    }
+
+    /// The distinct readings in ascending order, with their counts.
+    pub fn iter(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.counts.iter().map(|(&k, &n)| (value(k), n))
+    }
+}
+
+/// An integer that sorts like `num` does, with both zeros as one.
+fn key(num: f64) -> i64 {
+    let bits = (num + 0.0).to_bits() as i64;
+    bits ^ (((bits >> 63) as u64) >> 1) as i64
+}
+
+/// The reading `key` was made from.
+fn value(key: i64) -> f64 {
+    f64::from_bits((key ^ (((key >> 63) as u64) >> 1) as i64) as u64)
+}
+
+/// Serialized as `[reading, count]` pairs, like `Buckets`.
+impl Serialize for Others {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for Others {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let mut others = Others::default();
+
+        for (num, n) in Vec::<(f64, u64)>::deserialize(d)? {
+            if !num.is_finite() {
+                return Err(serde::de::Error::custom("histogram reading is not finite"));
+            }
+
+            others.add(num, n);
+        }
+
+        Ok(others)
+    }
+}
+
+impl HistogramResult {
+    /// Every distinct reading in ascending order, with how often it occurred.
+    fn values(&self) -> impl Iterator<Item = (Value, u64)> + '_ {
+        let mut buckets = self
+            .buckets
+            .iter()
+            .map(|(tenths, n)| (Tenths(tenths as i64), n))
+            .peekable();
+        let mut others = self.others.iter().peekable();
+
+        std::iter::from_fn(move || match (buckets.peek(), others.peek()) {
+            (Some((t, _)), Some(&(o, _))) if t.as_f64() <= o => {
+                buckets.next().map(|(t, n)| (Value::Tenths(t), n))
+            }
+            (_, Some(_)) => others.next().map(|(o, n)| (Value::Float(o), n)),
+            (Some(_), None) => buckets.next().map(|(t, n)| (Value::Tenths(t), n)),
+            (None, None) => None,
+        })
+    }
+
+    /// The nearest-rank `p`th percentile: the smallest reading with at least
+    /// `p` percent of all readings at or below it. Always an actual reading.
+    pub fn percentile(&self, p: f64) -> Option<Value> {
+        // Nudge down so binary noise in p * count can't bump the rank.
+        let rank = ((p * self.count as f64 / 100.0) - 1e-9).ceil().max(1.0) as u64;
+        let mut seen = 0;
+
+        self.values().find_map(|(value, n)| {
+            seen += n;
+            (seen >= rank).then_some(value)
+        })
+    }
+
+    /// The most frequent reading, the lowest one if several tie.
+    pub fn mode(&self) -> Option<Value> {
+        self.values()
+            .fold(None, |best: Option<(Value, u64)>, (value, n)| match best {
+                Some((_, m)) if m >= n => best,
+                _ => Some((value, n)),
+            })
+            .map(|(value, _)| value)
+    }
+}
+
+/// A temperature in tenths of a degree, displayed with one decimal place.
+#[derive(Clone, Copy)]
+pub struct Tenths(pub i64);
+
+impl Tenths {
+    pub fn round(num: f64) -> Self {
+        Tenths((num * 10.0 + 0.5).floor() as i64)
+    }
+
+    pub fn as_f64(self) -> f64 {
+        self.0 as f64 / 10.0
+    }
+}
+
+impl fmt::Display for Tenths {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Formatting the integer parts separately never yields "-0.0".
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+
+        write!(f, "{}{}.{}", sign, abs / 10, abs % 10)
+    }
+}
+
+/// A statistic that can be requested with `--stats`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stat {
+    Count,
+    Sum,
+    Min,
+    Mean,
+    Max,
+    Variance,
+    Stddev,
+    Skewness,
+    Kurtosis,
+    Median,
+    Mode,
+    /// A percentile between 0 (exclusive) and 100, written e.g. `p99.9`.
+    Percentile(f64),
+}
+
+/// What `run` reports when no `--stats` are given.
+pub const DEFAULT_STATS: &[Stat] = &[Stat::Min, Stat::Mean, Stat::Max];
+
+const STATS: &[Stat] = &[
+    Stat::Count,
+    Stat::Sum,
+    Stat::Min,
+    Stat::Mean,
+    Stat::Max,
+    Stat::Variance,
+    Stat::Stddev,
+    Stat::Skewness,
+    Stat::Kurtosis,
+    Stat::Median,
+    Stat::Mode,
+];
+
+impl Stat {
+    fn needs_moments(self) -> bool {
+        matches!(
+            self,
+            Stat::Variance | Stat::Stddev | Stat::Skewness | Stat::Kurtosis
+        )
+    }
+
+    fn needs_histogram(self) -> bool {
+        matches!(self, Stat::Median | Stat::Mode | Stat::Percentile(_))
+    }
+}
+
+impl FromStr for Stat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        if let Some(p) = s.strip_prefix('p') {
+            return match p.parse::<f64>() {
+                Ok(p) if p > 0.0 && p <= 100.0 => Ok(Stat::Percentile(p)),
+                _ => Err(format!(
+                    "invalid percentile {:?}, expected p followed by a number in (0, 100]",
+                    s
+                )),
+            };
+        }
+
+        STATS
+            .iter()
+            .find(|stat| stat.to_string() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<String> = STATS.iter().map(Stat::to_string).collect();
+                format!(
+                    "unknown statistic {:?}, expected one of {} or a percentile like p90",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for Stat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Stat::Count => "count",
+            Stat::Sum => "sum",
+            Stat::Min => "min",
+            Stat::Mean => "mean",
+            Stat::Max => "max",
+            Stat::Variance => "variance",
+            Stat::Stddev => "stddev",
+            Stat::Skewness => "skewness",
+            Stat::Kurtosis => "kurtosis",
+            Stat::Median => "median",
+            Stat::Mode => "mode",
+            Stat::Percentile(p) => return write!(f, "p{}", p),
+        })
+    }
+}
+
+/// The aggregators needed to compute some set of `Stat`s. `Nums` is always
+/// kept; the rest only when a requested statistic needs them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub nums: Nums,
+    pub moments: Option<Moments>,
+    pub histogram: Option<Histogram>,
+}
+
+/// Final values of `Stats`.
+pub struct StatsResult {
+    pub nums: NumsResult,
+    pub moments: Option<MomentsResult>,
+    pub histogram: Option<HistogramResult>,
+}
+
+/// A single finished statistic.
+#[derive(Clone, Copy)]
+pub enum Value {
+    Count(u64),
+    Tenths(Tenths),
+    Float(f64),
+}
+
+impl Stats {
+    pub fn new(stats: &[Stat]) -> Self {
+        Stats {
+            nums: Nums::new(),
+            moments: stats.iter().any(|s| s.needs_moments()).then(Moments::new),
+            histogram: stats
+                .iter()
+                .any(|s| s.needs_histogram())
+                .then(Histogram::new),
+        }
+    }
+}
+
+impl From<Nums> for Stats {
+    fn from(nums: Nums) -> Self {
+        Stats {
+            nums,
+            moments: None,
+            histogram: None,
+        }
+    }
+}
+
+/// Merges optional aggregators. If only one side has one, the result would
+/// silently cover just part of the readings, so it is dropped instead.
+fn merge_optional<A: Aggregator>(a: &mut Option<A>, b: &Option<A>) {
+    match (a.as_mut(), b) {
+        (Some(a), Some(b)) => a.merge(b),
+        (Some(_), None) => *a = None,
+        (None, _) => {}
+    }
+}
+
+impl Aggregator for Stats {
+    type Output = StatsResult;
+
+    fn update(&mut self, tenths: i16) {
+        self.nums.update(tenths);
+
+        if let Some(m) = &mut self.moments {
+            m.update(tenths);
+        }
+
+        if let Some(h) = &mut self.histogram {
+            h.update(tenths);
+        }
+    }
+
+    fn update_f64(&mut self, num: f64) {
+        self.nums.update_f64(num);
+
+        if let Some(m) = &mut self.moments {
+            m.update_f64(num);
+        }
+
+        if let Some(h) = &mut self.histogram {
+            h.update_f64(num);
+        }
+    }
+
+    fn merge(&mut self, other: &Stats) {
+        self.nums.merge(&other.nums);
+        merge_optional(&mut self.moments, &other.moments);
+        merge_optional(&mut self.histogram, &other.histogram);
+    }
+
+    fn finish(&self) -> StatsResult {
+        StatsResult {
+            nums: self.nums.finish(),
+            moments: self.moments.as_ref().map(Moments::finish),
+            histogram: self.histogram.as_ref().map(Histogram::finish),
+        }
+    }
+}
+
+impl StatsResult {
+    /// The value of `stat`, or `None` if it wasn't collected.
+    pub fn get(&self, stat: Stat) -> Option<Value> {
+        Some(match stat {
+            Stat::Count => Value::Count(self.nums.count),
+            Stat::Sum => Value::Tenths(self.nums.sum),
+            Stat::Min => Value::Tenths(self.nums.min),
+            Stat::Mean => Value::Tenths(self.nums.mean),
+            Stat::Max => Value::Tenths(self.nums.max),
+            Stat::Variance => Value::Float(self.moments?.variance),
+            Stat::Stddev => Value::Float(self.moments?.stddev),
+            Stat::Skewness => Value::Float(self.moments?.skewness),
+            Stat::Kurtosis => Value::Float(self.moments?.kurtosis),
+            Stat::Median => self.histogram.as_ref()?.percentile(50.0)?,
+            Stat::Mode => self.histogram.as_ref()?.mode()?,
+            Stat::Percentile(p) => self.histogram.as_ref()?.percentile(p)?,
+        })
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Count(n) => n.fmt(f),
+            Value::Tenths(t) => t.fmt(f),
+            Value::Float(x) => write!(f, "{:.3}", x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A skewed set of readings, mixing one-decimal and arbitrary values.
+    const READINGS: &[f64] = &[
+        12.3, -4.5, 0.0, 31.7, 31.7, 8.25, -17.0, 99.9, -99.9, 2.5, 2.5, 2.5, 44.4, 7.125, -0.1,
+        15.0, 60.3, -33.3, 1.0, 1e-3,
+    ];
+
+    fn moments(readings: &[f64]) -> Moments {
+        let mut m = Moments::new();
+
+        for &x in readings {
+            m.update_f64(x);
+        }
+
+        m
+    }
+
+    fn assert_close(a: f64, b: f64, what: &str) {
+        let scale = a.abs().max(b.abs()).max(1.0);
+        assert!((a - b).abs() <= 1e-9 * scale, "{}: {} != {}", what, a, b);
+    }
+
+    fn assert_same(a: &Moments, b: &Moments) {
+        assert_eq!(a.count, b.count);
+        assert_close(a.mean, b.mean, "mean");
+        assert_close(a.m2, b.m2, "m2");
+        assert_close(a.m3, b.m3, "m3");
+        assert_close(a.m4, b.m4, "m4");
+    }
+
+    /// Welford's updates against the textbook two-pass sums.
+    #[test]
+    fn moments_match_two_pass_sums() {
+        let m = moments(READINGS);
+        let n = READINGS.len() as f64;
+        let mean = READINGS.iter().sum::<f64>() / n;
+        let central = |k: i32| READINGS.iter().map(|x| (x - mean).powi(k)).sum::<f64>();
+
+        assert_close(m.mean, mean, "mean");
+        assert_close(m.m2, central(2), "m2");
+        assert_close(m.m3, central(3), "m3");
+        assert_close(m.m4, central(4), "m4");
+    }
+
+    #[test]
+    fn merge_equals_sequential_at_every_split() {
+        let all = moments(READINGS);
+
+        for split in 0..=READINGS.len() {
+            let mut m = moments(&READINGS[..split]);
+            m.merge(&moments(&READINGS[split..]));
+            assert_same(&m, &all);
+        }
+    }
+
+    #[test]
+    fn merge_equals_sequential_over_many_chunks() {
+        let all = moments(READINGS);
+
+        for size in 1..READINGS.len() {
+            let mut m = Moments::new();
+
+            for chunk in READINGS.chunks(size) {
+                m.merge(&moments(chunk));
+            }
+
+            assert_same(&m, &all);
+        }
+    }
+
+    #[test]
+    fn merge_with_empty_is_identity() {
+        let all = moments(READINGS);
+
+        let mut m = Moments::new();
+        m.merge(&all);
+        assert_same(&m, &all);
+
+        let mut m = all.clone();
+        m.merge(&Moments::new());
+        assert_same(&m, &all);
+    }
+
+    #[test]
+    fn tenths_update_like_their_value() {
+        let mut a = Moments::new();
+        let mut b = Moments::new();
+
+        for tenths in [-999, -1, 0, 5, 123, 999] {
+            a.update(tenths);
+            b.update_f64(tenths as f64 / 10.0);
+        }
+
+        assert_same(&a, &b);
+    }
+
+    fn histogram(tenths: &[i16], floats: &[f64]) -> HistogramResult {
+        let mut h = Histogram::new();
+
+        for &t in tenths {
+            h.update(t);
+        }
+
+        for &x in floats {
+            h.update_f64(x);
+        }
+
+        h.finish()
+    }
+
+    fn shown(value: Option<Value>) -> Option<String> {
+        value.map(|v| v.to_string())
+    }
+
+    #[test]
+    fn percentiles_of_a_single_reading() {
+        let h = histogram(&[-73], &[]);
+
+        for p in [0.1, 1.0, 50.0, 99.9, 100.0] {
+            assert_eq!(shown(h.percentile(p)).as_deref(), Some("-7.3"), "p{}", p);
+        }
+
+        assert_eq!(shown(h.mode()).as_deref(), Some("-7.3"));
+    }
+
+    /// With ten readings each one owns exactly a tenth of the ranks, so a
+    /// percentile on a multiple of 10 is the reading at that rank and
+    /// anything above it is the next one.
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let h = histogram(&[10, 20, 30, 40, 50, 60, 70, 80, 90, 100], &[]);
+
+        let cases = [
+            (1.0, "1.0"),
+            (10.0, "1.0"),
+            (10.1, "2.0"),
+            (50.0, "5.0"),
+            (50.5, "6.0"),
+            (90.0, "9.0"),
+            (99.0, "10.0"),
+            (100.0, "10.0"),
+        ];
+
+        for (p, expected) in cases {
+            assert_eq!(shown(h.percentile(p)).as_deref(), Some(expected), "p{}", p);
+        }
+    }
+
+    #[test]
+    fn percentiles_count_repeated_readings() {
+        let h = histogram(&[5, 5, 5, -5], &[]);
+
+        assert_eq!(shown(h.percentile(25.0)).as_deref(), Some("-0.5"));
+        assert_eq!(shown(h.percentile(25.1)).as_deref(), Some("0.5"));
+        assert_eq!(shown(h.percentile(100.0)).as_deref(), Some("0.5"));
+    }
+
+    #[test]
+    fn percentiles_interleave_float_readings() {
+        let h = histogram(&[10, 30], &[2.25, 0.5]);
+
+        assert_eq!(shown(h.percentile(25.0)).as_deref(), Some("0.500"));
+        assert_eq!(shown(h.percentile(50.0)).as_deref(), Some("1.0"));
+        assert_eq!(shown(h.percentile(75.0)).as_deref(), Some("2.250"));
+        assert_eq!(shown(h.percentile(100.0)).as_deref(), Some("3.0"));
+    }
+
+    #[test]
+    fn percentile_and_mode_of_nothing() {
+        let h = histogram(&[], &[]);
+
+        assert!(h.percentile(50.0).is_none());
+        assert!(h.mode().is_none());
+    }
+
+    #[test]
+    fn mode_picks_the_lowest_of_tied_readings() {
+        let h = histogram(&[30, 30, -20, -20, 10], &[]);
+        assert_eq!(shown(h.mode()).as_deref(), Some("-2.0"));
+
+        let h = histogram(&[30, 30, 10], &[-2.5, -2.5]);
+        assert_eq!(shown(h.mode()).as_deref(), Some("-2.500"));
+
+        let h = histogram(&[30, 30, 30, 10], &[-2.5, -2.5]);
+        assert_eq!(shown(h.mode()).as_deref(), Some("3.0"));
+    }
+
+    #[test]
+    fn buckets_merge_like_sequential_adds() {
+        let readings = [0, -999, 999, 5, 5, -3, 120, -999];
+        let mut all = Buckets::default();
+
+        for &t in &readings {
+            all.add(t, 1);
+        }
+
+        for split in 0..=readings.len() {
+            let (mut a, mut b) = (Buckets::default(), Buckets::default());
+
+            for &t in &readings[..split] {
+                a.add(t, 1);
+            }
+
+            for &t in &readings[split..] {
+                b.add(t, 1);
+            }
+
+            a.merge(&b);
+            assert_eq!(a.iter().collect::<Vec<_>>(), all.iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn buckets_round_trip_through_json() {
+        let mut buckets = Buckets::default();
+        buckets.add(-12, 3);
+        buckets.add(40, 1);
+
+        let json = serde_json::to_string(&buckets).unwrap();
+        assert_eq!(json, "[[-12,3],[40,1]]");
+
+        let back: Buckets = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.iter().collect::<Vec<_>>(), vec![(-12, 3), (40, 1)]);
+        assert!(serde_json::from_str::<Buckets>("[[1000,1]]").is_err());
+    }
+
+    #[test]
+    fn others_count_distinct_readings_in_order() {
+        let readings = [
+            2.25,
+            -0.0,
+            0.0,
+            -1e300,
+            1e300,
+            -2.25,
+            2.25,
+            f64::MIN_POSITIVE,
+            -5e-324,
+            2.25,
+        ];
+        let mut others = Others::default();
+
+        for &x in &readings {
+            others.add(x, 1);
+        }
+
+        let mut sorted = readings.map(|x| x + 0.0);
+        sorted.sort_by(f64::total_cmp);
+        let mut expected: Vec<(f64, u64)> = Vec::new();
+
+        for x in sorted {
+            match expected.last_mut() {
+                Some((last, n)) if *last == x => *n += 1,
+                _ => expected.push((x, 1)),
+            }
+        }
+
+        assert_eq!(others.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(others.counts.len(), 7);
+    }
+
+    #[test]
+    fn others_merge_and_round_trip_through_json() {
+        let (mut a, mut b) = (Others::default(), Others::default());
+        a.add(0.25, 2);
+        a.add(-1.125, 1);
+        b.add(0.25, 1);
+        b.add(7.5, 4);
+        a.merge(&b);
+
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, "[[-1.125,1],[0.25,3],[7.5,4]]");
+
+        let back: Others = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            back.iter().collect::<Vec<_>>(),
+            a.iter().collect::<Vec<_>>()
+        );
+    }
+}
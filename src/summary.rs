@@ -0,0 +1,191 @@
+//! Aggregating measurements, from a buffer or from any reader.
+//!
+//! Each function takes a prototype aggregator that new stations start from
+//! as a clone, and an `OnError` policy for lines that can't be read.
+
+use rayon::prelude::*;
+use std::io::Read;
+
+use crate::error::Result;
+use crate::reject::{parse_name, OnError, Reading, Reason, Reject, Rejects};
+use crate::scan::{for_each_window, Row, Rows};
+use crate::stats::Aggregator;
+use crate::table::StationTable;
+
+/// Stations aggregated over some span of the input, along with the lines
+/// that were rejected.
+pub struct Summary<A> {
+    pub nums: StationTable<A>,
+    pub rejects: Rejects,
+    pub rows: u64,
+    lines: u64,
+}
+
+impl<A: Aggregator> Summary<A> {
+    pub fn new() -> Self {
+        Summary {
+            nums: StationTable::new(),
+            rejects: Rejects::default(),
+            rows: 0,
+            lines: 0,
+        }
+    }
+
+    /// Adds a row, starting new stations from a clone of `proto`.
+    pub fn add(&mut self, row: &Row, proto: &A) -> Result<(), Reason> {
+        let (id, num) = row.split().ok_or(Reason::MissingSeparator)?;
+        let reading = Reading::parse(num)?;
+
+        // Names are only validated the first time they're seen.
+        let n = self
+            .nums
+            .try_get_or_insert_with(id, || parse_name(id).map(|_| proto.clone()))?;
+
+        reading.add_to(n);
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Adds the summary of the input that follows ours.
+    pub fn merge(&mut self, other: Summary<A>) {
+        self.nums.merge(other.nums, |n, o| n.merge(&o));
+        self.rejects.merge(other.rejects, self.lines);
+        self.rows += other.rows;
+        self.lines += other.lines;
+    }
+}
+
+impl<A: Aggregator> Default for Summary<A> {
+    fn default() -> Self {
+        Summary::new()
+    }
+}
+
+/// Aggregates `data` on the current thread.
+pub fn aggregate<A: Aggregator>(
+    data: &[u8],
+    proto: &A,
+    on_error: OnError,
+) -> Result<Summary<A>, Reject> {
+    let mut summary = Summary::new();
+    calculate_chunk(&mut summary, data, 0, proto, on_error)?;
+    Ok(summary)
+}
+
+/// Aggregates newline-aligned chunks of `data` on every thread of the
+/// current rayon pool.
+pub fn aggregate_parallel<A: Aggregator>(
+    data: &[u8],
+    proto: &A,
+    on_error: OnError,
+) -> Result<Summary<A>, Reject> {
+    calculate_window(data, 0, proto, on_error)
+}
+
+/// Aggregates everything `reader` produces, holding a bounded window of it
+/// in memory at a time. With `parallel`, each window is split across every
+/// thread like `aggregate_parallel` does.
+pub fn aggregate_reader<A: Aggregator, R: Read>(
+    reader: R,
+    proto: &A,
+    on_error: OnError,
+    parallel: bool,
+) -> Result<Summary<A>> {
+    let mut summary = Summary::new();
+
+    for_each_window(reader, |window, offset| -> Result<()> {
+        if parallel {
+            let window =
+                calculate_window(window, offset, proto, on_error).map_err(|mut reject| {
+                    reject.line += summary.lines;
+                    reject
+                })?;
+
+            summary.merge(window);
+        } else {
+            calculate_chunk(&mut summary, window, offset, proto, on_error)?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(summary)
+}
+
+/// Aggregates `data`, which starts `offset` bytes into the input, in
+/// parallel chunks.
+fn calculate_window<A: Aggregator>(
+    data: &[u8],
+    offset: u64,
+    proto: &A,
+    on_error: OnError,
+) -> Result<Summary<A>, Reject> {
+    split_chunks(data, rayon::current_num_threads() * 4)
+        .into_par_iter()
+        // Each worker aggregates the consecutive chunks it takes into one
+        // summary, rather than starting a table per chunk.
+        .try_fold(Summary::new, |mut summary, (start, chunk)| {
+            calculate_chunk(&mut summary, chunk, offset + start as u64, proto, on_error)?;
+            Ok(summary)
+        })
+        .reduce(
+            || Ok(Summary::new()),
+            |a, b: Result<Summary<A>, Reject>| match (a, b) {
+                (Ok(mut summary), Ok(other)) => {
+                    summary.merge(other);
+                    Ok(summary)
+                }
+                // Report the first bad line whichever chunk finds one first.
+                (Err(e), _) => Err(e),
+                (Ok(summary), Err(mut e)) => {
+                    e.line += summary.lines;
+                    Err(e)
+                }
+            },
+        )
+}
+
+/// Splits `data` into roughly `n` chunks that each end on a newline, so that
+/// every line falls entirely within one chunk. Each chunk comes with its
+/// offset into `data`.
+fn split_chunks(data: &[u8], n: usize) -> Vec<(usize, &[u8])> {
+    let size = (data.len() / n.max(1)).max(1);
+    let mut chunks = Vec::with_capacity(n);
+    let mut start = 0;
+
+    while start < data.len() {
+        let mut end = (start + size).min(data.len());
+
+        while end < data.len() && data[end - 1] != b'\n' {
+            end += 1;
+        }
+
+        chunks.push((start, &data[start..end]));
+        start = end;
+    }
+
+    chunks
+}
+
+/// Adds a chunk that starts `offset` bytes into the input, and follows
+/// whatever `summary` already covers.
+fn calculate_chunk<A: Aggregator>(
+    summary: &mut Summary<A>,
+    chunk: &[u8],
+    offset: u64,
+    proto: &A,
+    on_error: OnError,
+) -> Result<(), Reject> {
+    let mut rows = Rows::new(chunk);
+
+    for row in &mut rows {
+        if let Err(reason) = summary.add(&row, proto) {
+            let mut reject = Reject::new(&row, offset, reason);
+            reject.line += summary.lines;
+            summary.rejects.add(reject, on_error)?;
+        }
+    }
+
+    summary.lines += rows.lines();
+    Ok(())
+}
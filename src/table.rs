@@ -0,0 +1,283 @@
+/// Open-addressing hash table keyed on raw station name bytes.
+///
+/// Names are copied once into a shared arena when they are first seen, so
+/// lookups in the hot loop never allocate. Probing is linear over a
+/// power-of-two slot array that only stores indexes into `entries`.
+pub struct StationTable<V> {
+    slots: Vec<u32>,
+    entries: Vec<Entry<V>>,
+    names: Vec<u8>,
+}
+
+struct Entry<V> {
+    hash: u64,
+    start: usize,
+    len: usize,
+    value: V,
+}
+
+impl<V> Default for StationTable<V> {
+    fn default() -> Self {
+        StationTable::new()
+    }
+}
+
+const EMPTY: u32 = u32::MAX;
+const INITIAL_SLOTS: usize = 1 << 12;
+
+impl<V> StationTable<V> {
+    pub fn new() -> Self {
+        StationTable {
+            slots: vec![EMPTY; INITIAL_SLOTS],
+            entries: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    pub fn get_or_insert_with(&mut self, name: &[u8], f: impl FnOnce() -> V) -> &mut V {
+        match self.try_get_or_insert_with(name, || Ok::<_, ()>(f())) {
+            Ok(v) => v,
+            Err(()) => unreachable!(),
+        }
+    }
+
+    /// Like `get_or_insert_with`, but `f` may refuse to create an entry for
+    /// a new name, in which case nothing is inserted.
+    pub fn try_get_or_insert_with<E>(
+        &mut self,
+        name: &[u8],
+        f: impl FnOnce() -> Result<V, E>,
+    ) -> Result<&mut V, E> {
+        let hash = hash(name);
+        let slot = self.find(hash, name);
+
+        let idx = match self.slots[slot] {
+            EMPTY => self.insert(slot, hash, name, f()?),
+            idx => idx as usize,
+        };
+
+        Ok(&mut self.entries[idx].value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &V)> {
+        self.entries
+            .iter()
+            .map(|e| (&self.names[e.start..e.start + e.len], &e.value))
+    }
+
+    /// Folds every entry of `other` into this table, combining values for
+    /// names present in both with `f`.
+    pub fn merge(&mut self, other: StationTable<V>, mut f: impl FnMut(&mut V, V)) {
+        let names = other.names;
+
+        for e in other.entries {
+            let name = &names[e.start..e.start + e.len];
+            let slot = self.find(e.hash, name);
+
+            match self.slots[slot] {
+                EMPTY => {
+                    self.insert(slot, e.hash, name, e.value);
+                }
+                idx => f(&mut self.entries[idx as usize].value, e.value),
+            }
+        }
+    }
+
+    /// Returns the slot holding `name`, or the empty slot where it belongs.
+    fn find(&self, hash: u64, name: &[u8]) -> usize {
+        let mask = self.slots.len() - 1;
+        let mut slot = hash as usize & mask;
+
+        loop {
+            match self.slots[slot] {
+                EMPTY => return slot,
+                idx => {
+                    let e = &self.entries[idx as usize];
+
+                    if e.hash == hash
+                        && e.len == name.len()
+                        && &self.names[e.start..e.start + e.len] == name
+                    {
+                        return slot;
+                    }
+                }
+            }
+
+            slot = (slot + 1) & mask;
+        }
+    }
+
+    fn insert(&mut self, slot: usize, hash: u64, name: &[u8], value: V) -> usize {
+        let idx = self.entries.len();
+        let start = self.names.len();
+
+        self.names.extend_from_slice(name);
+        self.entries.push(Entry {
+            hash,
+            start,
+            len: name.len(),
+            value,
+        });
+        self.slots[slot] = idx as u32;
+
+        if self.entries.len() * 2 > self.slots.len() {
+            self.grow();
+        }
+
+        idx
+    }
+
+    fn grow(&mut self) {
+        let mut slots = vec![EMPTY; self.slots.len() * 2];
+        let mask = slots.len() - 1;
+
+        for (idx, e) in self.entries.iter().enumerate() {
+            let mut slot = e.hash as usize & mask;
+
+            while slots[slot] != EMPTY {
+                slot = (slot + 1) & mask;
+            }
+
+            slots[slot] = idx as u32;
+        }
+
+        self.slots = slots;
+    }
+}
+
+/// Cheap multiply-rotate hash over 8-byte words of the name.
+fn hash(name: &[u8]) -> u64 {
+    const K: u64 = 0x517c_c1b7_2722_0a95;
+
+    let mut h = name.len() as u64;
+    let mut words = name.chunks_exact(8);
+
+    for w in &mut words {
+        h = (h.rotate_left(5) ^ u64::from_le_bytes(w.try_into().unwrap())).wrapping_mul(K);
+    }
+
+    let rest = words.remainder();
+
+    if !rest.is_empty() {
+        let mut buf = [0u8; 8];
+        buf[..rest.len()].copy_from_slice(rest);
+        h = (h.rotate_left(5) ^ u64::from_le_bytes(buf)).wrapping_mul(K);
+    }
+
+    h ^ (h >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Names that share prefixes, differ only in their last byte or come in
+    /// the same lengths, around the 8-byte words `hash` reads.
+    fn names(n: usize) -> Vec<Vec<u8>> {
+        (0..n)
+            .map(|i| match i % 4 {
+                0 => format!("station{}", i).into_bytes(),
+                1 => format!("{:08}", i).into_bytes(),
+                2 => "x".repeat(i % 40 + 1).into_bytes(),
+                _ => format!("Ngaoundéré {:>3}", i % 1000).into_bytes(),
+            })
+            .collect()
+    }
+
+    fn contents(table: &StationTable<u64>) -> HashMap<Vec<u8>, u64> {
+        table.iter().map(|(name, &v)| (name.to_vec(), v)).collect()
+    }
+
+    /// Crosses `INITIAL_SLOTS / 2` several times over.
+    #[test]
+    fn counts_like_a_hash_map() {
+        let names = names(20_000);
+        let mut table = StationTable::new();
+        let mut expected = HashMap::new();
+        let mut order = Vec::new();
+
+        for (i, name) in names
+            .iter()
+            .enumerate()
+            .chain(names.iter().enumerate().step_by(3))
+        {
+            let v = table.get_or_insert_with(name, || {
+                order.push(name.clone());
+                0
+            });
+            *v += i as u64;
+            *expected.entry(name.clone()).or_insert(0) += i as u64;
+        }
+
+        assert!(table.slots.len() > INITIAL_SLOTS);
+        assert_eq!(contents(&table), expected);
+
+        // Entries come back in the order they were first inserted.
+        let iterated: Vec<Vec<u8>> = table.iter().map(|(name, _)| name.to_vec()).collect();
+        assert_eq!(iterated, order);
+    }
+
+    #[test]
+    fn probes_past_names_in_the_same_slot() {
+        let mask = INITIAL_SLOTS as u64 - 1;
+        let slot = hash(b"station0") & mask;
+        let mut colliding: Vec<Vec<u8>> = names(200_000)
+            .into_iter()
+            .filter(|name| hash(name) & mask == slot)
+            .collect();
+        colliding.sort();
+        colliding.dedup();
+        colliding.truncate(8);
+        assert!(colliding.len() > 2);
+
+        let mut table = StationTable::new();
+
+        for (i, name) in colliding.iter().enumerate() {
+            *table.get_or_insert_with(name, || 0) += i as u64 + 1;
+        }
+
+        for (i, name) in colliding.iter().enumerate() {
+            assert_eq!(*table.get_or_insert_with(name, || 0), i as u64 + 1);
+        }
+
+        assert_eq!(table.iter().count(), colliding.len());
+    }
+
+    #[test]
+    fn merge_combines_overlapping_tables() {
+        let names = names(6_000);
+        let (mut a, mut b) = (StationTable::new(), StationTable::new());
+        let mut expected = HashMap::new();
+
+        for (i, name) in names[..4_000].iter().enumerate() {
+            *a.get_or_insert_with(name, || 0) += i as u64;
+            *expected.entry(name.clone()).or_insert(0) += i as u64;
+        }
+
+        for (i, name) in names[2_000..].iter().enumerate() {
+            *b.get_or_insert_with(name, || 0) += i as u64 * 7;
+            *expected.entry(name.clone()).or_insert(0) += i as u64 * 7;
+        }
+
+        a.merge(b, |v, o| *v += o);
+        assert_eq!(contents(&a), expected);
+    }
+
+    #[test]
+    fn refused_entries_are_not_inserted() {
+        let mut table: StationTable<u64> = StationTable::new();
+
+        assert_eq!(
+            table.try_get_or_insert_with(b"bad", || Err("no")).err(),
+            Some("no")
+        );
+        assert_eq!(table.iter().count(), 0);
+        assert!(table.names.is_empty());
+
+        *table
+            .try_get_or_insert_with(b"bad", || Ok::<_, ()>(1))
+            .unwrap() += 1;
+        assert_eq!(contents(&table), HashMap::from([(b"bad".to_vec(), 2)]));
+    }
+}
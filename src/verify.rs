@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use brc::stats::DEFAULT_STATS;
+use brc::{Error, OnError, Result, Results, Stats};
+
+use crate::cli::VerifyArgs;
+use crate::{aggregate, path_error};
+
+/// One station's line from a results file. The count is only known for
+/// results printed with `--counts`.
+#[derive(Clone, Copy)]
+pub struct StationResult {
+    pub count: Option<u64>,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+pub fn verify(args: &VerifyArgs) -> Result<()> {
+    let expected = read_results(&args.expected)?;
+
+    let actual = match &args.actual {
+        Some(path) => read_results(path)?,
+        None => {
+            let summary = aggregate(
+                &args.input,
+                args.parallel,
+                &Stats::new(DEFAULT_STATS),
+                OnError::Skip,
+                None,
+            )?;
+
+            Results::new(&summary.nums)
+                .iter()
+                .map(|(id, result)| {
+                    let n = &result.nums;
+                    let result = StationResult {
+                        count: Some(n.count),
+                        min: n.min.as_f64(),
+                        mean: n.mean.as_f64(),
+                        max: n.max.as_f64(),
+                    };
+
+                    (id.to_string(), result)
+                })
+                .collect()
+        }
+    };
+
+    let differences = compare(&expected, &actual, args.epsilon);
+
+    if !differences.is_empty() {
+        // The verdict stands even if whoever reads the report stops early,
+        // like `head` does.
+        let mut out = io::stdout().lock();
+        let _ = differences
+            .iter()
+            .try_for_each(|line| writeln!(out, "{}", line))
+            .and_then(|_| out.flush());
+
+        return Err(Error::Mismatch(format!(
+            "{} of {} expected stations differ",
+            differences.len(),
+            expected.len()
+        )));
+    }
+
+    eprintln!("All {} stations match", expected.len());
+    Ok(())
+}
+
+/// Describes every difference between the two result sets, one line per
+/// station affected.
+fn compare(
+    expected: &BTreeMap<String, StationResult>,
+    actual: &BTreeMap<String, StationResult>,
+    epsilon: f64,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    for (id, e) in expected {
+        let Some(a) = actual.get(id) else {
+            differences.push(format!("{}: missing", id));
+            continue;
+        };
+
+        let mut fields = Vec::new();
+
+        if let (Some(ec), Some(ac)) = (e.count, a.count) {
+            if ec != ac {
+                fields.push(format!("count expected {}, got {}", ec, ac));
+            }
+        }
+
+        for (field, ev, av) in [
+            ("min", e.min, a.min),
+            ("mean", e.mean, a.mean),
+            ("max", e.max, a.max),
+        ] {
+            // Values only carry one decimal, so allow for binary noise.
+            if (ev - av).abs() > epsilon + 1e-9 {
+                fields.push(format!("{} expected {:.1}, got {:.1}", field, ev, av));
+            }
+        }
+
+        if !fields.is_empty() {
+            differences.push(format!("{}: {}", id, fields.join(", ")));
+        }
+    }
+
+    for id in actual.keys().filter(|id| !expected.contains_key(*id)) {
+        differences.push(format!("{}: unexpected", id));
+    }
+
+    differences
+}
+
+/// Reads results in either the 1BRC `{name=min/mean/max, ...}` format or the
+/// one `name=count/min/mean/max` per line format printed with `--counts`.
+pub fn read_results(path: &Path) -> Result<BTreeMap<String, StationResult>> {
+    let text = std::fs::read_to_string(path).map_err(path_error(path))?;
+    let text = text.trim();
+
+    let parsed = match text.strip_prefix('{') {
+        Some(rest) => rest
+            .strip_suffix('}')
+            .ok_or_else(|| "missing closing `}`".to_string())
+            .and_then(parse_braced),
+        None => parse_lines(text),
+    };
+
+    parsed.map_err(|e| Error::parse(path, e))
+}
+
+fn parse_braced(text: &str) -> Result<BTreeMap<String, StationResult>, String> {
+    let mut results = BTreeMap::new();
+    let mut rest = text;
+
+    // Names may themselves contain `=`, `/` or `, `, so an entry ends at the
+    // first `=` that is followed by three numbers and then a separator.
+    while !rest.is_empty() {
+        let entry = rest
+            .match_indices('=')
+            .find_map(|(i, _)| {
+                let tail = &rest[i + 1..];
+                let end = tail.find(", ").unwrap_or(tail.len());
+
+                parse_values(&tail[..end], false).map(|result| {
+                    let next = (i + 1 + end + 2).min(rest.len());
+                    (&rest[..i], result, next)
+                })
+            })
+            .ok_or_else(|| format!("invalid entry near {:?}", truncate(rest)))?;
+
+        let (id, result, next) = entry;
+        results.insert(id.to_string(), result);
+        rest = &rest[next..];
+    }
+
+    Ok(results)
+}
+
+fn parse_lines(text: &str) -> Result<BTreeMap<String, StationResult>, String> {
+    let mut results = BTreeMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+
+        let result = line
+            .rsplit_once('=')
+            .and_then(|(id, values)| {
+                parse_values(values, true)
+                    .or_else(|| parse_values(values, false))
+                    .map(|r| (id, r))
+            })
+            .ok_or_else(|| format!("line {}: invalid entry {:?}", i + 1, truncate(line)))?;
+
+        results.insert(result.0.to_string(), result.1);
+    }
+
+    Ok(results)
+}
+
+/// Parses `min/mean/max`, or `count/min/mean/max` when `counts` is set.
+fn parse_values(s: &str, counts: bool) -> Option<StationResult> {
+    let mut parts = s.split('/');
+
+    let count = if counts {
+        Some(parts.next()?.parse().ok()?)
+    } else {
+        None
+    };
+
+    let result = StationResult {
+        count,
+        min: parts.next()?.parse().ok()?,
+        mean: parts.next()?.parse().ok()?,
+        max: parts.next()?.parse().ok()?,
+    };
+
+    parts.next().is_none().then_some(result)
+}
+
+fn truncate(s: &str) -> &str {
+    match s.char_indices().nth(40) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_lists_every_difference() {
+        let expected = parse_lines("a=1/1.0/2.0/3.0\nb=1/1.0/1.0/1.0\nc=2/0.0/0.5/1.0").unwrap();
+        let actual = parse_lines("a=1/1.0/2.0/3.0\nc=3/0.0/0.5/1.1\nd=1/0.0/0.0/0.0").unwrap();
+
+        assert_eq!(
+            compare(&expected, &actual, 0.0),
+            [
+                "b: missing",
+                "c: count expected 2, got 3, max expected 1.0, got 1.1",
+                "d: unexpected",
+            ]
+        );
+        assert!(compare(&expected, &expected, 0.0).is_empty());
+    }
+}